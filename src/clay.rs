@@ -0,0 +1,905 @@
+//! An [`ErasureCode`] implementation of Clay codes (minimum storage regenerating codes).
+//!
+//! [`ErasureCode`]: ../trait.ErasureCode.html
+use std::num::NonZeroUsize;
+use std::ops::Range;
+
+use crate::gf256;
+use crate::header::{self, FragmentHeader, Payloads};
+use crate::plan::{self, FragmentRange};
+use crate::verify;
+use crate::{
+    BuildCoder, ErasureCode, ErrorKind, Fragment, FragmentBuf, FragmentInfo, Result, VerifyReport,
+};
+
+/// The coefficient used for coupling a pair of sub-chunks.
+const GAMMA: u8 = 2;
+
+/// An [`ErasureCode`] implementation of Clay (coupled-layer) codes.
+///
+/// Clay codes are minimum storage regenerating (MSR) codes:
+/// the storage overhead is the same as Reed-Solomon codes (any `N` of `N + M` fragments can decode the data),
+/// but a single lost fragment can be reconstructed by reading only `1 / M` of each of the other fragments.
+///
+/// The payload of each fragment is split into [`sub_chunks`] sub-chunks.
+/// [`repair_plan`] returns the byte ranges of the helper fragments
+/// that are required to reconstruct a fragment,
+/// and [`reconstruct_partial`] reconstructs the fragment from those ranges.
+///
+/// [`ErasureCode`]: ../trait.ErasureCode.html
+/// [`sub_chunks`]: ./struct.ClayCoder.html#method.sub_chunks
+/// [`repair_plan`]: ../trait.ErasureCode.html#method.repair_plan
+/// [`reconstruct_partial`]: ../trait.ErasureCode.html#method.reconstruct_partial
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::{ErasureCode, ErrorKind};
+/// use ecpool::clay::ClayCoder;
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data_fragments = NonZeroUsize::new(4).ok_or("invalid input")?;
+/// let parity_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let mut coder = ClayCoder::new(data_fragments, parity_fragments)?;
+///
+/// // Encodes
+/// let data = vec![0; 1024];
+/// let encoded = coder.encode(&data)?;
+///
+/// // Reconstructs the fragment #0 by reading the half of each of the other fragments
+/// let available = (1..6).collect::<Vec<_>>();
+/// let plan = coder.repair_plan(0, &available, encoded[0].len())?;
+/// let parts = plan
+///     .iter()
+///     .map(|r| (r.clone(), &encoded[r.index][r.range.clone()]))
+///     .collect::<Vec<_>>();
+/// let read = parts.iter().map(|(_, bytes)| bytes.len()).sum::<usize>();
+/// assert!(read < 5 * encoded[0].len() * 2 / 3);
+/// assert_eq!(coder.reconstruct_partial(0, &parts)?, encoded[0]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClayCoder {
+    data_fragments: NonZeroUsize,
+    parity_fragments: NonZeroUsize,
+    shortened: usize,
+    sub_chunks: usize,
+    parity_matrix: Vec<Vec<u8>>,
+}
+impl ClayCoder {
+    /// The maximum number of total fragments.
+    pub const MAX_FRAGMENTS: usize = 256;
+
+    /// The maximum number of sub-chunks per fragment.
+    pub const MAX_SUB_CHUNKS: usize = 4096;
+
+    /// Makes a new `ClayCoder` instance.
+    ///
+    /// If the total number of fragments exceeds `MAX_FRAGMENTS`,
+    /// or the number of sub-chunks exceeds `MAX_SUB_CHUNKS`,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(data_fragments: NonZeroUsize, parity_fragments: NonZeroUsize) -> Result<Self> {
+        let k = data_fragments.get();
+        let m = parity_fragments.get();
+        let shortened = (m - (k + m) % m) % m;
+        track_assert!(
+            k + m + shortened <= Self::MAX_FRAGMENTS,
+            ErrorKind::InvalidInput,
+            "Too many fragments: data_fragments={}, parity_fragments={}",
+            k,
+            m
+        );
+        let t = (k + m + shortened) / m;
+        let sub_chunks = (0..t).try_fold(1usize, |a, _| {
+            a.checked_mul(m).filter(|&a| a <= Self::MAX_SUB_CHUNKS)
+        });
+        let sub_chunks = track_assert_some!(
+            sub_chunks,
+            ErrorKind::InvalidInput,
+            "Too many sub-chunks: data_fragments={}, parity_fragments={}",
+            k,
+            m
+        );
+        Ok(ClayCoder {
+            data_fragments,
+            parity_fragments,
+            shortened,
+            sub_chunks,
+            parity_matrix: gf256::cauchy_matrix(m, k + shortened),
+        })
+    }
+
+    /// Returns the number of sub-chunks that the payload of each fragment is split into.
+    pub fn sub_chunks(&self) -> usize {
+        self.sub_chunks
+    }
+
+    fn q(&self) -> usize {
+        self.parity_fragments.get()
+    }
+
+    /// Returns the number of nodes including the shortened (always zero) ones.
+    fn nodes(&self) -> usize {
+        self.fragments().get() + self.shortened
+    }
+
+    fn node_of(&self, index: usize) -> usize {
+        if index < self.data_fragments.get() {
+            index
+        } else {
+            index + self.shortened
+        }
+    }
+
+    fn index_of(&self, node: usize) -> Option<usize> {
+        let k = self.data_fragments.get();
+        if node < k {
+            Some(node)
+        } else if node < k + self.shortened {
+            None
+        } else {
+            Some(node - self.shortened)
+        }
+    }
+
+    fn digit(&self, plane: usize, y: usize) -> usize {
+        plane / self.q().pow(y as u32) % self.q()
+    }
+
+    fn with_digit(&self, plane: usize, y: usize, x: usize) -> usize {
+        let unit = self.q().pow(y as u32);
+        plane - self.digit(plane, y) * unit + x * unit
+    }
+
+    /// Returns the node and plane coupled with the given ones,
+    /// or `None` if the sub-chunk is not coupled.
+    fn companion(&self, node: usize, plane: usize) -> Option<(usize, usize)> {
+        let (x, y) = (node % self.q(), node / self.q());
+        let z = self.digit(plane, y);
+        if z == x {
+            None
+        } else {
+            Some((y * self.q() + z, self.with_digit(plane, y, x)))
+        }
+    }
+
+    /// Returns the planes used for reconstructing the given node.
+    fn repair_planes(&self, node: usize) -> Vec<usize> {
+        let (x, y) = (node % self.q(), node / self.q());
+        (0..self.sub_chunks)
+            .filter(|&z| self.digit(z, y) == x)
+            .collect()
+    }
+
+    fn generator_row(&self, node: usize) -> Vec<u8> {
+        let k = self.data_fragments.get() + self.shortened;
+        if node < k {
+            (0..k).map(|i| (i == node) as u8).collect()
+        } else {
+            self.parity_matrix[node - k].clone()
+        }
+    }
+
+    fn payload_len(&self, data_len: usize) -> usize {
+        let k = self.data_fragments.get();
+        data_len.div_ceil(k).div_ceil(self.sub_chunks) * self.sub_chunks
+    }
+
+    /// Selects the nodes used for decoding each plane and
+    /// returns those and the coefficients for computing the erased nodes from them.
+    fn decoding_coefficients(&self, erased: &[usize]) -> (Vec<usize>, Vec<Vec<u8>>) {
+        let k = self.data_fragments.get() + self.shortened;
+        let chosen = (0..self.nodes())
+            .filter(|i| !erased.contains(i))
+            .take(k)
+            .collect::<Vec<_>>();
+        let matrix = chosen
+            .iter()
+            .map(|&i| self.generator_row(i))
+            .collect::<Vec<_>>();
+        let inverse = gf256::invert_matrix(&matrix).expect("Never fails");
+        let coefficients = erased
+            .iter()
+            .map(|&e| {
+                let row = self.generator_row(e);
+                (0..k)
+                    .map(|c| {
+                        row.iter()
+                            .zip(inverse.iter())
+                            .fold(0, |acc, (&a, b)| acc ^ gf256::mul(a, b[c]))
+                    })
+                    .collect()
+            })
+            .collect();
+        (chosen, coefficients)
+    }
+
+    /// Computes the payloads of the erased (i.e., `None`) nodes by decoding all of the planes.
+    ///
+    /// The number of the erased nodes must be at most `M`.
+    fn decode_layers(&self, payloads: &[Option<&[u8]>], sub_len: usize) -> Vec<Vec<u8>> {
+        let sub = |z: usize| z * sub_len..(z + 1) * sub_len;
+        let erased = (0..self.nodes())
+            .filter(|&i| payloads[i].is_none())
+            .collect::<Vec<_>>();
+        let (chosen, coefficients) = self.decoding_coefficients(&erased);
+
+        // Uncoupled sub-chunks. The planes are processed in the ascending order of
+        // the number of the uncoupled erased sub-chunks, so that the companions of
+        // the sub-chunks in a plane have been decoded in the preceding planes.
+        let mut uncoupled = vec![vec![0; self.sub_chunks * sub_len]; self.nodes()];
+        let mut planes = (0..self.sub_chunks).collect::<Vec<_>>();
+        planes.sort_by_key(|&z| {
+            erased
+                .iter()
+                .filter(|&&e| self.companion(e, z).is_none())
+                .count()
+        });
+        for z in planes {
+            for (i, payload) in payloads.iter().enumerate() {
+                let Some(payload) = payload else { continue };
+                let mut layer = payload[sub(z)].to_vec();
+                if let Some((j, zs)) = self.companion(i, z) {
+                    match payloads[j] {
+                        Some(c) => uncouple(&mut layer, &c[sub(zs)]),
+                        None => gf256::mul_slice_xor(GAMMA, &uncoupled[j][sub(zs)], &mut layer),
+                    }
+                }
+                uncoupled[i][sub(z)].copy_from_slice(&layer);
+            }
+            for (&e, row) in erased.iter().zip(coefficients.iter()) {
+                let mut layer = vec![0; sub_len];
+                for (&c, &j) in row.iter().zip(chosen.iter()) {
+                    gf256::mul_slice_xor(c, &uncoupled[j][sub(z)], &mut layer);
+                }
+                uncoupled[e][sub(z)].copy_from_slice(&layer);
+            }
+        }
+
+        erased
+            .iter()
+            .map(|&e| {
+                let mut payload = uncoupled[e].clone();
+                for z in 0..self.sub_chunks {
+                    if let Some((j, zs)) = self.companion(e, z) {
+                        gf256::mul_slice_xor(GAMMA, &uncoupled[j][sub(zs)], &mut payload[sub(z)]);
+                    }
+                }
+                payload
+            })
+            .collect()
+    }
+
+    /// Reconstructs the payload of the `lost` node from the sub-chunks in its repair planes.
+    ///
+    /// `read(node, plane)` returns the sub-chunk of the given helper node.
+    fn repair_layers<'a, F>(&self, lost: usize, sub_len: usize, read: F) -> Result<Vec<u8>>
+    where
+        F: Fn(usize, usize) -> Option<&'a [u8]>,
+    {
+        let q = self.q();
+        let (x0, y0) = (lost % q, lost / q);
+        let gamma_inv = gf256::inv(GAMMA);
+        let planes = self.repair_planes(lost);
+        let mut position = vec![usize::MAX; self.sub_chunks];
+        for (p, &z) in planes.iter().enumerate() {
+            position[z] = p;
+        }
+
+        let zeros = vec![0; sub_len];
+        let mut helpers = vec![Vec::new(); self.nodes()];
+        for (i, h) in helpers.iter_mut().enumerate().filter(|&(i, _)| i != lost) {
+            for &z in &planes {
+                let chunk = if self.index_of(i).is_none() {
+                    &zeros[..]
+                } else {
+                    track_assert_some!(
+                        read(i, z),
+                        ErrorKind::InvalidInput,
+                        "Missing sub-chunk: index={:?}, sub_chunk={}",
+                        self.index_of(i),
+                        z
+                    )
+                };
+                h.push(chunk);
+            }
+        }
+        let chunk = |i: usize, z: usize| helpers[i][position[z]];
+
+        // The sub-chunks of the lost node's column are unknown in every repair plane.
+        let column = (0..q).map(|x| y0 * q + x).collect::<Vec<_>>();
+        let (chosen, coefficients) = self.decoding_coefficients(&column);
+        let mut payload = vec![0; self.sub_chunks * sub_len];
+        for &z in &planes {
+            let mut uncoupled = vec![Vec::new(); self.nodes()];
+            for i in (0..self.nodes()).filter(|i| i / q != y0) {
+                let mut layer = chunk(i, z).to_vec();
+                if let Some((j, zs)) = self.companion(i, z) {
+                    uncouple(&mut layer, chunk(j, zs));
+                }
+                uncoupled[i] = layer;
+            }
+            for (&e, row) in column.iter().zip(coefficients.iter()) {
+                let mut layer = vec![0; sub_len];
+                for (&c, &j) in row.iter().zip(chosen.iter()) {
+                    gf256::mul_slice_xor(c, &uncoupled[j], &mut layer);
+                }
+                uncoupled[e] = layer;
+            }
+
+            payload[z * sub_len..][..sub_len].copy_from_slice(&uncoupled[lost]);
+            for x in (0..q).filter(|&x| x != x0) {
+                let a = y0 * q + x;
+                let zs = self.with_digit(z, y0, x);
+
+                // C_a(z) = U_a(z) + γ U_lost(zs) and C_lost(zs) = U_lost(zs) + γ U_a(z)
+                let mut layer = chunk(a, z).to_vec();
+                gf256::mul_slice_xor(1, &uncoupled[a], &mut layer);
+                for b in layer.iter_mut() {
+                    *b = gf256::mul(gamma_inv, *b);
+                }
+                gf256::mul_slice_xor(GAMMA, &uncoupled[a], &mut layer);
+                payload[zs * sub_len..][..sub_len].copy_from_slice(&layer);
+            }
+        }
+        Ok(payload)
+    }
+
+    fn check_payload_len(&self, data_len: usize, payload_len: usize) -> Result<()> {
+        track_assert_eq!(
+            payload_len,
+            self.payload_len(data_len),
+            ErrorKind::CorruptedFragments,
+            "Unexpected fragment size: coder={}, data_len={}",
+            self.coder_id(),
+            data_len
+        );
+        Ok(())
+    }
+
+    fn node_payloads<'a>(
+        &self,
+        slots: &[Option<&'a [u8]>],
+        zeros: &'a [u8],
+    ) -> Vec<Option<&'a [u8]>> {
+        (0..self.nodes())
+            .map(|i| match self.index_of(i) {
+                Some(index) => slots[index],
+                None => Some(zeros),
+            })
+            .collect()
+    }
+
+    fn decode_payloads(&self, payloads: &Payloads) -> Result<Vec<u8>> {
+        let k = self.data_fragments.get();
+        track!(self.check_payload_len(payloads.data_len, payloads.payload_len))?;
+        track_assert!(
+            payloads.available() >= k,
+            ErrorKind::InvalidInput,
+            "Too few fragments: available={}, data_fragments={}",
+            payloads.available(),
+            k
+        );
+
+        let mut data = Vec::with_capacity(k * payloads.payload_len);
+        if payloads.slots[..k].iter().all(|s| s.is_some()) {
+            for s in &payloads.slots[..k] {
+                data.extend_from_slice(s.expect("Never fails"));
+            }
+        } else {
+            let zeros = vec![0; payloads.payload_len];
+            let nodes = self.node_payloads(&payloads.slots, &zeros);
+            let mut recovered = self
+                .decode_layers(&nodes, payloads.payload_len / self.sub_chunks)
+                .into_iter();
+            for s in &payloads.slots[..k] {
+                match s {
+                    Some(p) => data.extend_from_slice(p),
+                    None => data.extend(recovered.next().expect("Never fails")),
+                }
+            }
+        }
+        data.truncate(payloads.data_len);
+        Ok(data)
+    }
+
+    fn reconstruct_payloads(&self, index: usize, payloads: &Payloads) -> Result<Vec<u8>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        track!(self.check_payload_len(payloads.data_len, payloads.payload_len))?;
+        let sub_len = payloads.payload_len / self.sub_chunks;
+        let lost = self.node_of(index);
+
+        let helpers = (0..self.fragments().get()).filter(|&i| i != index);
+        let payload = if helpers.clone().all(|i| payloads.slots[i].is_some()) {
+            track!(self.repair_layers(lost, sub_len, |i, z| {
+                let payload = payloads.slots[self.index_of(i)?]?;
+                Some(&payload[z * sub_len..(z + 1) * sub_len])
+            }))?
+        } else {
+            let mut slots = payloads.slots.clone();
+            slots[index] = None;
+            let available = slots.iter().filter(|s| s.is_some()).count();
+            track_assert!(
+                available >= self.data_fragments.get(),
+                ErrorKind::InvalidInput,
+                "Too few fragments: available={}, data_fragments={}",
+                available,
+                self.data_fragments
+            );
+            let zeros = vec![0; payloads.payload_len];
+            let nodes = self.node_payloads(&slots, &zeros);
+            let erased = (0..lost).filter(|&i| nodes[i].is_none()).count();
+            self.decode_layers(&nodes, sub_len).swap_remove(erased)
+        };
+        Ok(Self::make_fragment(index, payloads.data_len, &payload))
+    }
+
+    fn make_fragment(index: usize, data_len: usize, payload: &[u8]) -> FragmentBuf {
+        let mut fragment = vec![0; FragmentHeader::SIZE];
+        FragmentHeader::new(index, data_len).write_to(&mut fragment);
+        fragment.extend_from_slice(payload);
+        fragment
+    }
+}
+impl ErasureCode for ClayCoder {
+    fn data_fragments(&self) -> NonZeroUsize {
+        self.data_fragments
+    }
+
+    fn parity_fragments(&self) -> NonZeroUsize {
+        self.parity_fragments
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<FragmentBuf>> {
+        let fragment_size = FragmentHeader::SIZE + self.payload_len(data.len());
+        let mut fragments = vec![vec![0; fragment_size]; self.fragments().get()];
+        let mut buffers = fragments.iter_mut().map(|f| &mut f[..]).collect::<Vec<_>>();
+        track!(self.encode_into(data, &mut buffers))?;
+        Ok(fragments)
+    }
+
+    fn fragment_size(&self, data_len: usize) -> Option<usize> {
+        Some(FragmentHeader::SIZE + self.payload_len(data_len))
+    }
+
+    fn encode_into(&mut self, data: &[u8], buffers: &mut [&mut [u8]]) -> Result<()> {
+        let k = self.data_fragments.get();
+        let payload_len = self.payload_len(data.len());
+        track!(crate::check_buffers(
+            buffers,
+            self.fragments().get(),
+            Some(FragmentHeader::SIZE + payload_len)
+        ))?;
+        let (data_buffers, parity_buffers) = buffers.split_at_mut(k);
+        header::write_data_fragments(data, payload_len, data_buffers);
+        let zeros = vec![0; payload_len];
+        let slots = (0..self.fragments().get())
+            .map(|i| data_buffers.get(i).map(|b| &b[FragmentHeader::SIZE..]))
+            .collect::<Vec<_>>();
+        let parities = self.decode_layers(
+            &self.node_payloads(&slots, &zeros),
+            payload_len / self.sub_chunks,
+        );
+        for (i, (buffer, parity)) in parity_buffers.iter_mut().zip(parities).enumerate() {
+            FragmentHeader::new(k + i, data.len()).write_to(buffer);
+            buffer[FragmentHeader::SIZE..].copy_from_slice(&parity);
+        }
+        Ok(())
+    }
+
+    fn update_parity(
+        &mut self,
+        data_index: usize,
+        old_data: &Fragment,
+        new_data: &Fragment,
+        parities: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        let k = self.data_fragments.get();
+        track_assert_eq!(
+            parities.len(),
+            self.parity_fragments.get(),
+            ErrorKind::InvalidInput
+        );
+        let delta = track!(header::parity_delta(
+            data_index, old_data, new_data, parities, k
+        ))?;
+        let data_len = track!(FragmentHeader::read_from(old_data))?.data_len;
+        track!(self.check_payload_len(data_len, delta.len()))?;
+
+        // Since the code is linear, the parities of the delta are the differences of the parities
+        let zeros = vec![0; delta.len()];
+        let slots = (0..self.fragments().get())
+            .map(|i| match i {
+                _ if i == data_index => Some(&delta[..]),
+                _ if i < k => Some(&zeros[..]),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let deltas = self.decode_layers(
+            &self.node_payloads(&slots, &zeros),
+            delta.len() / self.sub_chunks,
+        );
+        Ok(parities
+            .iter()
+            .zip(deltas.iter())
+            .map(|(parity, delta)| {
+                let mut parity = parity.to_vec();
+                gf256::mul_slice_xor(1, delta, &mut parity[FragmentHeader::SIZE..]);
+                parity
+            })
+            .collect())
+    }
+
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        track!(header::fragment_info(fragment, "clay"))
+    }
+
+    fn verify(&mut self, fragments: &[&Fragment]) -> VerifyReport {
+        verify::verify(self, fragments, FragmentHeader::SIZE, header::fragment_meta)
+    }
+
+    fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.decode_payloads(&payloads))
+    }
+
+    fn reconstruct_many(
+        &mut self,
+        indices: &[usize],
+        fragments: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        for &index in indices {
+            track_assert!(
+                index < self.fragments().get(),
+                ErrorKind::Other,
+                "Too large index: index={}, fragments={}",
+                index,
+                self.fragments()
+            );
+        }
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        if let [index] = *indices {
+            return track!(self.reconstruct_payloads(index, &payloads)).map(|f| vec![f]);
+        }
+        track!(self.check_payload_len(payloads.data_len, payloads.payload_len))?;
+
+        let mut slots = payloads.slots.clone();
+        for &index in indices {
+            slots[index] = None;
+        }
+        let available = slots.iter().filter(|s| s.is_some()).count();
+        track_assert!(
+            available >= self.data_fragments.get(),
+            ErrorKind::InvalidInput,
+            "Too few fragments: available={}, data_fragments={}",
+            available,
+            self.data_fragments
+        );
+        let zeros = vec![0; payloads.payload_len];
+        let nodes = self.node_payloads(&slots, &zeros);
+        let erased = (0..self.nodes())
+            .filter(|&i| nodes[i].is_none())
+            .collect::<Vec<_>>();
+        let recovered = self.decode_layers(&nodes, payloads.payload_len / self.sub_chunks);
+        Ok(indices
+            .iter()
+            .map(|&index| {
+                let position = erased
+                    .iter()
+                    .position(|&e| e == self.node_of(index))
+                    .expect("Never fails");
+                Self::make_fragment(index, payloads.data_len, &recovered[position])
+            })
+            .collect())
+    }
+
+    fn decode_indexed(&mut self, fragments: &[(usize, &Fragment)]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_indexed_fragments(
+            fragments,
+            self.fragments().get()
+        ))?;
+        track!(self.decode_payloads(&payloads))
+    }
+
+    fn decode_range(&mut self, range: Range<usize>, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(plan::check_data_range(&range, payloads.data_len))?;
+        track!(self.check_payload_len(payloads.data_len, payloads.payload_len))?;
+        let ranges = plan::split_range(range.clone(), payloads.payload_len);
+        if ranges.iter().any(|(i, _)| payloads.slots[*i].is_none()) {
+            // The sub-chunks of a data fragment are coupled with those of other fragments,
+            // so a missing data fragment can only be recovered as a whole.
+            let data = track!(self.decode_payloads(&payloads))?;
+            return Ok(data[range].to_vec());
+        }
+        let mut data = Vec::with_capacity(range.len());
+        for (i, r) in ranges {
+            data.extend_from_slice(&payloads.slots[i].expect("Never fails")[r]);
+        }
+        Ok(data)
+    }
+
+    fn fragment_ranges(&self, range: Range<usize>, data_len: usize) -> Option<Vec<FragmentRange>> {
+        let payload_len = self.payload_len(data_len);
+        plan::data_fragment_ranges(range, data_len, payload_len, FragmentHeader::SIZE)
+    }
+
+    fn reconstruct(&mut self, index: usize, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.reconstruct_payloads(index, &payloads))
+    }
+
+    fn reconstruct_indexed(
+        &mut self,
+        index: usize,
+        fragments: &[(usize, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_indexed_fragments(
+            fragments,
+            self.fragments().get()
+        ))?;
+        track!(self.reconstruct_payloads(index, &payloads))
+    }
+
+    fn repair_plan(
+        &self,
+        index: usize,
+        available: &[usize],
+        fragment_len: usize,
+    ) -> Result<Vec<FragmentRange>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        let helpers = (0..self.fragments().get()).filter(|&i| i != index);
+        if !helpers.clone().all(|i| available.contains(&i)) {
+            let k = self.data_fragments.get();
+            return track!(plan::full_repair_plan(index, available, k, fragment_len));
+        }
+
+        let payload_len = fragment_len.saturating_sub(FragmentHeader::SIZE);
+        track_assert!(
+            fragment_len >= FragmentHeader::SIZE && payload_len.is_multiple_of(self.sub_chunks),
+            ErrorKind::InvalidInput,
+            "Unexpected fragment size: fragment_len={}, sub_chunks={}",
+            fragment_len,
+            self.sub_chunks
+        );
+        let sub_len = payload_len / self.sub_chunks;
+        let mut ranges = Vec::new();
+        let mut last = 0..FragmentHeader::SIZE;
+        for z in self.repair_planes(self.node_of(index)) {
+            let start = FragmentHeader::SIZE + z * sub_len;
+            if last.end == start {
+                last.end += sub_len;
+            } else {
+                ranges.push(std::mem::replace(&mut last, start..start + sub_len));
+            }
+        }
+        ranges.push(last);
+        Ok(helpers
+            .flat_map(|i| ranges.iter().map(move |r| FragmentRange::new(i, r.clone())))
+            .collect())
+    }
+
+    fn reconstruct_partial(
+        &mut self,
+        index: usize,
+        parts: &[(FragmentRange, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        let mut data_len = None;
+        let mut helpers = Vec::new();
+        for (r, _) in parts {
+            if helpers.contains(&r.index) {
+                continue;
+            }
+            let Some(bytes) = plan::find_range(parts, r.index, 0..FragmentHeader::SIZE) else {
+                continue;
+            };
+            let header = track!(FragmentHeader::read_from(bytes))?;
+            track_assert_eq!(
+                header.index,
+                r.index,
+                ErrorKind::CorruptedFragments,
+                "Unexpected fragment index"
+            );
+            track_assert_eq!(
+                header.data_len,
+                *data_len.get_or_insert(header.data_len),
+                ErrorKind::CorruptedFragments,
+                "Inconsistent data size: index={}",
+                header.index
+            );
+            helpers.push(r.index);
+        }
+        let data_len = track_assert_some!(data_len, ErrorKind::InvalidInput, "No fragments");
+
+        let all_helpers = (0..self.fragments().get())
+            .filter(|&i| i != index)
+            .all(|i| helpers.contains(&i));
+        if !all_helpers {
+            let fragment_len = FragmentHeader::SIZE + self.payload_len(data_len);
+            let fragments = parts
+                .iter()
+                .filter(|(r, f)| r.range == (0..fragment_len) && f.len() == fragment_len)
+                .map(|(_, f)| *f)
+                .collect::<Vec<_>>();
+            return track!(self.reconstruct(index, &fragments));
+        }
+
+        let sub_len = self.payload_len(data_len) / self.sub_chunks;
+        let payload = track!(self.repair_layers(self.node_of(index), sub_len, |i, z| {
+            let start = FragmentHeader::SIZE + z * sub_len;
+            plan::find_range(parts, self.index_of(i)?, start..start + sub_len)
+        }))?;
+        Ok(Self::make_fragment(index, data_len, &payload))
+    }
+}
+impl BuildCoder for ClayCoder {
+    type Coder = Self;
+
+    fn build_coder(&self) -> Result<Self::Coder> {
+        Ok(self.clone())
+    }
+
+    fn coder_id(&self) -> String {
+        format!("clay:{}:{}", self.data_fragments, self.parity_fragments)
+    }
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for ClayCoder {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let repr = crate::serialize::FragmentsRepr::new(self.data_fragments, self.parity_fragments);
+        serde::Serialize::serialize(&repr, serializer)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ClayCoder {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr: crate::serialize::FragmentsRepr = crate::serialize::deserialize(deserializer)?;
+        ClayCoder::new(repr.data_fragments, repr.parity_fragments)
+            .map_err(crate::serialize::invalid)
+    }
+}
+
+/// Computes `layer = (layer + γ * companion) / (1 + γ^2)`.
+fn uncouple(layer: &mut [u8], companion: &[u8]) {
+    gf256::mul_slice_xor(GAMMA, companion, layer);
+    let scale = gf256::inv(1 ^ gf256::mul(GAMMA, GAMMA));
+    for b in layer.iter_mut() {
+        *b = gf256::mul(scale, *b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::MainError;
+
+    use super::*;
+    use crate::test_util::{non_zero, test_data};
+    use crate::{ErasureCode, ErrorKind};
+
+    #[test]
+    fn it_works() -> Result<(), MainError> {
+        let mut coder = track!(ClayCoder::new(non_zero(5)?, non_zero(3)?))?;
+        assert_eq!(coder.sub_chunks(), 27);
+
+        let data = test_data(1000);
+        let encoded = track!(coder.encode(&data))?;
+        let encoded = encoded.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+
+        assert_eq!(Some(&data), coder.decode(&encoded[0..]).as_ref().ok());
+        assert_eq!(Some(&data), coder.decode(&encoded[3..]).as_ref().ok());
+        assert_eq!(
+            Some(&data),
+            coder
+                .decode(&[encoded[0], encoded[2], encoded[5], encoded[6], encoded[7]])
+                .as_ref()
+                .ok()
+        );
+        assert_eq!(
+            Err(ErrorKind::InvalidInput),
+            coder.decode(&encoded[4..]).map_err(|e| *e.kind())
+        );
+
+        let fragments = [encoded[0], encoded[2], encoded[4], encoded[5], encoded[7]];
+        assert_eq!(
+            track!(coder.decode_range(150..450, &fragments))?,
+            &data[150..450]
+        );
+
+        let reconstructed = track!(coder.reconstruct_many(&[1, 6, 3], &fragments))?;
+        assert_eq!(reconstructed, vec![encoded[1], encoded[6], encoded[3]]);
+        Ok(())
+    }
+
+    #[test]
+    fn reconstruct_partial_works() -> Result<(), MainError> {
+        for &(k, m) in &[(4, 2), (5, 3)] {
+            let mut coder = track!(ClayCoder::new(non_zero(k)?, non_zero(m)?))?;
+            let data = test_data(1000);
+            let encoded = track!(coder.encode(&data))?;
+            let fragment_len = encoded[0].len();
+            let n = k + m;
+            for index in 0..n {
+                // From `1 / M` of all of the other fragments
+                let available = (0..n).filter(|&i| i != index).collect::<Vec<_>>();
+                let plan = track!(coder.repair_plan(index, &available, fragment_len))?;
+                let parts = plan
+                    .iter()
+                    .map(|r| (r.clone(), &encoded[r.index][r.range.clone()]))
+                    .collect::<Vec<_>>();
+                let payload_read = parts.iter().map(|(_, b)| b.len()).sum::<usize>()
+                    - (n - 1) * FragmentHeader::SIZE;
+                assert_eq!(
+                    payload_read,
+                    (n - 1) * (fragment_len - FragmentHeader::SIZE) / m
+                );
+                assert_eq!(
+                    track!(coder.reconstruct_partial(index, &parts))?,
+                    encoded[index]
+                );
+
+                // From the whole of `N` fragments
+                let available = available.into_iter().skip(m - 1).collect::<Vec<_>>();
+                let plan = track!(coder.repair_plan(index, &available, fragment_len))?;
+                assert_eq!(plan.len(), k);
+                let parts = plan
+                    .iter()
+                    .map(|r| (r.clone(), &encoded[r.index][r.range.clone()]))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    track!(coder.reconstruct_partial(index, &parts))?,
+                    encoded[index]
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn update_parity_works() -> Result<(), MainError> {
+        let mut coder = track!(ClayCoder::new(non_zero(5)?, non_zero(3)?))?;
+        let mut data = test_data(1000);
+        let old = track!(coder.encode(&data))?;
+
+        // Overwrites a part of the fourth data fragment
+        let payload_len = old[0].len() - FragmentHeader::SIZE;
+        for b in &mut data[3 * payload_len + 10..3 * payload_len + 50] {
+            *b ^= 0x5A;
+        }
+        let new = track!(coder.encode(&data))?;
+
+        let parities = old[5..].iter().map(|f| &f[..]).collect::<Vec<_>>();
+        assert_eq!(
+            track!(coder.update_parity(3, &old[3], &new[3], &parities))?,
+            new[5..].to_vec()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+//! CRC-32 (IEEE 802.3) checksums.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+static TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            c = if c & 1 != 0 {
+                POLYNOMIAL ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            j += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+/// Computes the standard CRC-32 checksum of `buf` (the same as zlib's `crc32(0, buf, len)`).
+pub fn crc32(buf: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in buf {
+        crc = TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// Computes the CRC-32 checksum in the same way as liberasurecode-1.5.0.
+///
+/// liberasurecode-1.5.0 holds the intermediate value in a signed integer,
+/// so the shift is arithmetic and the result differs from the standard one
+/// when the most significant bit is set during the computation.
+pub fn crc32_liberasurecode(buf: &[u8]) -> u32 {
+    let mut crc = !0i32;
+    for &b in buf {
+        crc = (TABLE[((crc ^ i32::from(b)) & 0xFF) as usize] as i32) ^ (crc >> 8);
+    }
+    !crc as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_works() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32_liberasurecode(b""), 0);
+    }
+}
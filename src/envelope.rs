@@ -0,0 +1,575 @@
+//! A self-describing envelope that can wrap the fragments produced by any [`ErasureCode`] implementation.
+//!
+//! Some coders (e.g., [`ReplicaCoder`]) produce raw fragments that cannot be identified from their contents.
+//! [`EnvelopeCoder`] prepends an [`EnvelopeHeader`] to each fragment produced by the inner coder,
+//! so that every fragment records which coder produced it and where it belongs,
+//! and the foreign, truncated or corrupted fragments can be rejected before decoding.
+//!
+//! [`ErasureCode`]: ../trait.ErasureCode.html
+//! [`ReplicaCoder`]: ../replica/struct.ReplicaCoder.html
+//! [`EnvelopeCoder`]: ./struct.EnvelopeCoder.html
+//! [`EnvelopeHeader`]: ./struct.EnvelopeHeader.html
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+
+use crate::crc32;
+use crate::verify::{self, FragmentMeta};
+use crate::{
+    BuildCoder, ErasureCode, ErrorKind, Fragment, FragmentBuf, FragmentInfo, Result, VerifyReport,
+};
+
+/// The magic number at the beginning of enveloped fragments.
+pub const MAGIC: [u8; 4] = *b"ECPL";
+
+/// The current version of the envelope format.
+pub const VERSION: u8 = 1;
+
+/// Returns the hash of the given coder identifier that is recorded in envelopes.
+///
+/// The hash is the CRC-32 checksum of the identifier, so it is stable across platforms and releases.
+pub fn coder_id_hash(coder_id: &str) -> u32 {
+    crc32::crc32(coder_id.as_bytes())
+}
+
+/// The header of an enveloped fragment.
+///
+/// Layout (little-endian):
+/// - `0..4`: magic number (`MAGIC`)
+/// - `4`: format version (`VERSION`)
+/// - `5..8`: reserved (zeros)
+/// - `8..12`: hash of the identifier of the inner coder (see [`coder_id_hash`])
+/// - `12..16`: index of the fragment
+/// - `16..24`: size of the original data
+/// - `24..28`: CRC-32 checksum of the payload
+/// - `28..32`: CRC-32 checksum of the above bytes
+///
+/// [`coder_id_hash`]: ./fn.coder_id_hash.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EnvelopeHeader {
+    /// The hash of the identifier of the coder that produced the payload.
+    pub coder_id_hash: u32,
+
+    /// The index of the fragment.
+    pub index: usize,
+
+    /// The size of the original data.
+    pub data_len: usize,
+
+    /// The CRC-32 checksum of the payload.
+    pub checksum: u32,
+}
+impl EnvelopeHeader {
+    /// The size of the header in bytes.
+    pub const SIZE: usize = 32;
+
+    /// Parses the header at the beginning of the given fragment.
+    ///
+    /// If the fragment is too short (e.g., truncated) or the header is broken,
+    /// this will return an `ErrorKind::CorruptedFragments` error.
+    /// If the fragment does not start with `MAGIC` or has an unsupported version,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// Note that the payload checksum is not checked by this method (see [`is_intact`]).
+    ///
+    /// [`is_intact`]: #method.is_intact
+    pub fn parse(fragment: &Fragment) -> Result<Self> {
+        track_assert!(
+            fragment.len() >= Self::SIZE,
+            ErrorKind::CorruptedFragments,
+            "Too short fragment: len={}",
+            fragment.len()
+        );
+        track_assert!(
+            fragment[..MAGIC.len()] == MAGIC,
+            ErrorKind::InvalidInput,
+            "Not an enveloped fragment"
+        );
+        track_assert_eq!(
+            fragment[4],
+            VERSION,
+            ErrorKind::InvalidInput,
+            "Unsupported envelope version"
+        );
+        track_assert_eq!(
+            read_u32(&fragment[28..32]),
+            crc32::crc32(&fragment[..28]),
+            ErrorKind::CorruptedFragments,
+            "Header checksum mismatch"
+        );
+        Ok(EnvelopeHeader {
+            coder_id_hash: read_u32(&fragment[8..12]),
+            index: read_u32(&fragment[12..16]) as usize,
+            data_len: read_u64(&fragment[16..24]) as usize,
+            checksum: read_u32(&fragment[24..28]),
+        })
+    }
+
+    /// Writes the header to the first `SIZE` bytes of `buf`.
+    pub fn write_to(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&MAGIC);
+        buf[4] = VERSION;
+        buf[5..8].copy_from_slice(&[0; 3]);
+        buf[8..12].copy_from_slice(&self.coder_id_hash.to_le_bytes());
+        buf[12..16].copy_from_slice(&(self.index as u32).to_le_bytes());
+        buf[16..24].copy_from_slice(&(self.data_len as u64).to_le_bytes());
+        buf[24..28].copy_from_slice(&self.checksum.to_le_bytes());
+        let header_checksum = crc32::crc32(&buf[..28]);
+        buf[28..32].copy_from_slice(&header_checksum.to_le_bytes());
+    }
+
+    /// Returns `true` if the payload of the given fragment matches the checksum of this header.
+    pub fn is_intact(&self, fragment: &Fragment) -> bool {
+        fragment.len() >= Self::SIZE && crc32::crc32(&fragment[Self::SIZE..]) == self.checksum
+    }
+}
+
+/// An [`ErasureCode`] implementation that wraps the fragments produced by the inner coder in envelopes.
+///
+/// The decoding methods reject the fragments produced by other coders
+/// (with `ErrorKind::CoderMismatch`) and skip the truncated or corrupted ones.
+///
+/// [`ErasureCode`]: ../trait.ErasureCode.html
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::envelope::{EnvelopeCoderBuilder, EnvelopeHeader};
+/// use ecpool::replica::ReplicaCoder;
+/// use ecpool::{BuildCoder, ErasureCode, ErrorKind};
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let parity_fragments = NonZeroUsize::new(1).ok_or("invalid input")?;
+/// let builder = EnvelopeCoderBuilder::new(ReplicaCoder::new(data_fragments, parity_fragments));
+/// let mut coder = builder.build_coder()?;
+///
+/// let mut encoded = coder.encode(b"foo")?;
+/// assert_eq!(EnvelopeHeader::parse(&encoded[2])?.index, 2);
+///
+/// // Truncated fragments are skipped
+/// encoded[0].truncate(EnvelopeHeader::SIZE + 1);
+/// let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+/// assert_eq!(coder.decode(&fragments)?, b"foo");
+///
+/// // Raw fragments are rejected
+/// let raw = [0; EnvelopeHeader::SIZE + 3];
+/// assert_eq!(
+///     coder.decode(&[&raw[..], &raw[..]]).map_err(|e| *e.kind()),
+///     Err(ErrorKind::InvalidInput)
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvelopeCoder<C> {
+    inner: C,
+    coder_id: String,
+    coder_id_hash: u32,
+}
+impl<C: ErasureCode> EnvelopeCoder<C> {
+    /// Makes a new `EnvelopeCoder` instance.
+    ///
+    /// `coder_id` is the identifier of the inner coder (see [`BuildCoder::coder_id`]).
+    ///
+    /// [`BuildCoder::coder_id`]: ../trait.BuildCoder.html#tymethod.coder_id
+    pub fn new(inner: C, coder_id: &str) -> Self {
+        EnvelopeCoder {
+            inner,
+            coder_id: coder_id.to_owned(),
+            coder_id_hash: coder_id_hash(coder_id),
+        }
+    }
+
+    /// Returns a reference to the inner coder.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Takes ownership of the instance and returns the inner coder.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn seal(&self, index: usize, data_len: usize, payload: &[u8]) -> FragmentBuf {
+        let mut fragment = vec![0; EnvelopeHeader::SIZE + payload.len()];
+        fragment[EnvelopeHeader::SIZE..].copy_from_slice(payload);
+        self.header(index, data_len, payload)
+            .write_to(&mut fragment);
+        fragment
+    }
+
+    fn header(&self, index: usize, data_len: usize, payload: &[u8]) -> EnvelopeHeader {
+        EnvelopeHeader {
+            coder_id_hash: self.coder_id_hash,
+            index,
+            data_len,
+            checksum: crc32::crc32(payload),
+        }
+    }
+
+    /// Parses the envelope of the given fragment and returns the header and the payload.
+    fn open<'a>(&self, fragment: &'a Fragment) -> Result<(EnvelopeHeader, &'a Fragment)> {
+        let header = track!(EnvelopeHeader::parse(fragment))?;
+        track_assert_eq!(
+            header.coder_id_hash,
+            self.coder_id_hash,
+            ErrorKind::CoderMismatch,
+            "The fragment was produced by another coder: expected_coder={} (hash={:08x}), found_hash={:08x}",
+            self.coder_id,
+            self.coder_id_hash,
+            header.coder_id_hash
+        );
+        track_assert!(
+            header.index < self.fragments().get(),
+            ErrorKind::CoderMismatch,
+            "Too large index: coder={}, expected_fragments={}, found_index={}",
+            self.coder_id,
+            self.fragments(),
+            header.index
+        );
+        track_assert!(
+            header.is_intact(fragment),
+            ErrorKind::CorruptedFragments,
+            "Payload checksum mismatch: index={}",
+            header.index
+        );
+        Ok((header, &fragment[EnvelopeHeader::SIZE..]))
+    }
+
+    /// Opens the envelopes of the given fragments and returns the size of the original data and
+    /// the payloads of the intact fragments with their indices.
+    ///
+    /// Truncated or corrupted fragments (and the duplicates of the same index) are skipped.
+    /// The most common size of the original data among the fragments is regarded as the correct one,
+    /// and the fragments that disagree with it are also skipped.
+    fn open_all<'a>(
+        &self,
+        fragments: &[&'a Fragment],
+    ) -> Result<(usize, Vec<(usize, &'a Fragment)>)> {
+        let mut opened = Vec::with_capacity(fragments.len());
+        for fragment in fragments {
+            match self.open(fragment) {
+                Ok(x) => opened.push(x),
+                Err(ref e) if *e.kind() == ErrorKind::CorruptedFragments => {}
+                Err(e) => return Err(track!(e)),
+            }
+        }
+        let data_len = opened
+            .iter()
+            .map(|(h, _)| h.data_len)
+            .max_by_key(|&len| opened.iter().filter(|(h, _)| h.data_len == len).count());
+        let data_len = track_assert_some!(
+            data_len,
+            ErrorKind::CorruptedFragments,
+            "No intact fragments: fragments={}",
+            fragments.len()
+        );
+
+        let mut seen = HashSet::new();
+        let payloads = opened
+            .into_iter()
+            .filter(|(h, _)| h.data_len == data_len && seen.insert(h.index))
+            .map(|(h, payload)| (h.index, payload))
+            .collect();
+        Ok((data_len, payloads))
+    }
+}
+impl<C: ErasureCode> ErasureCode for EnvelopeCoder<C> {
+    fn data_fragments(&self) -> NonZeroUsize {
+        self.inner.data_fragments()
+    }
+
+    fn parity_fragments(&self) -> NonZeroUsize {
+        self.inner.parity_fragments()
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<FragmentBuf>> {
+        let encoded = track!(self.inner.encode(data))?;
+        Ok(encoded
+            .iter()
+            .enumerate()
+            .map(|(i, payload)| self.seal(i, data.len(), payload))
+            .collect())
+    }
+
+    fn fragment_size(&self, data_len: usize) -> Option<usize> {
+        self.inner
+            .fragment_size(data_len)
+            .map(|size| EnvelopeHeader::SIZE + size)
+    }
+
+    fn encode_into(&mut self, data: &[u8], buffers: &mut [&mut [u8]]) -> Result<()> {
+        track!(crate::check_buffers(
+            buffers,
+            self.fragments().get(),
+            self.fragment_size(data.len())
+        ))?;
+        track_assert!(
+            buffers.iter().all(|b| b.len() >= EnvelopeHeader::SIZE),
+            ErrorKind::InvalidInput,
+            "Too small buffer"
+        );
+        {
+            let mut payloads = buffers
+                .iter_mut()
+                .map(|b| &mut b[EnvelopeHeader::SIZE..])
+                .collect::<Vec<_>>();
+            track!(self.inner.encode_into(data, &mut payloads))?;
+        }
+        for (i, buffer) in buffers.iter_mut().enumerate() {
+            let header = self.header(i, data.len(), &buffer[EnvelopeHeader::SIZE..]);
+            header.write_to(buffer);
+        }
+        Ok(())
+    }
+
+    fn update_parity(
+        &mut self,
+        data_index: usize,
+        old_data: &Fragment,
+        new_data: &Fragment,
+        parities: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        let (old_header, old_payload) = track!(self.open(old_data))?;
+        let (new_header, new_payload) = track!(self.open(new_data))?;
+        track_assert_eq!(old_header.index, data_index, ErrorKind::InvalidInput);
+        track_assert_eq!(new_header.index, data_index, ErrorKind::InvalidInput);
+        let mut headers = Vec::with_capacity(parities.len());
+        let mut parity_payloads = Vec::with_capacity(parities.len());
+        for &parity in parities {
+            let (header, payload) = track!(self.open(parity))?;
+            headers.push(header);
+            parity_payloads.push(payload);
+        }
+        let updated = track!(self.inner.update_parity(
+            data_index,
+            old_payload,
+            new_payload,
+            &parity_payloads
+        ))?;
+        Ok(headers
+            .iter()
+            .zip(updated.iter())
+            .map(|(header, payload)| self.seal(header.index, new_header.data_len, payload))
+            .collect())
+    }
+
+    fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let (_, payloads) = track!(self.open_all(fragments))?;
+        track!(self.inner.decode_indexed(&payloads))
+    }
+
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        let header = track!(EnvelopeHeader::parse(fragment))?;
+        Ok(FragmentInfo {
+            index: Some(header.index),
+            data_len: Some(header.data_len),
+            payload_len: fragment.len() - EnvelopeHeader::SIZE,
+            checksum: Some("crc32".to_owned()),
+            backend: "envelope".to_owned(),
+        })
+    }
+
+    fn verify(&mut self, fragments: &[&Fragment]) -> VerifyReport {
+        let coder_id_hash = self.coder_id_hash;
+        verify::verify(self, fragments, EnvelopeHeader::SIZE, |fragment| {
+            EnvelopeHeader::parse(fragment)
+                .ok()
+                .filter(|header| header.coder_id_hash == coder_id_hash)
+                .map(|header| FragmentMeta {
+                    index: header.index,
+                    data_len: header.data_len,
+                    intact: header.is_intact(fragment),
+                })
+        })
+    }
+
+    fn reconstruct(&mut self, index: usize, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        let (data_len, payloads) = track!(self.open_all(fragments))?;
+        let payload = track!(self.inner.reconstruct_indexed(index, &payloads))?;
+        Ok(self.seal(index, data_len, &payload))
+    }
+
+    fn decode_indexed(&mut self, fragments: &[(usize, &Fragment)]) -> Result<Vec<u8>> {
+        let fragments = fragments.iter().map(|&(_, f)| f).collect::<Vec<_>>();
+        track!(self.decode(&fragments))
+    }
+
+    fn is_decodable(&self, available: &[usize]) -> bool {
+        self.inner.is_decodable(available)
+    }
+
+    fn decode_plan(&self, available: &[usize], costs: Option<&[u64]>) -> Result<Vec<usize>> {
+        track!(self.inner.decode_plan(available, costs))
+    }
+
+    fn reconstruct_plan(
+        &self,
+        index: usize,
+        available: &[usize],
+        costs: Option<&[u64]>,
+    ) -> Result<Vec<usize>> {
+        track!(self.inner.reconstruct_plan(index, available, costs))
+    }
+}
+
+/// [`EnvelopeCoder`] builder.
+///
+/// The identifier of the resulting coders is `"envelope:"` followed by the identifier of the inner coders.
+///
+/// [`EnvelopeCoder`]: ./struct.EnvelopeCoder.html
+#[derive(Debug, Clone)]
+pub struct EnvelopeCoderBuilder<B> {
+    inner: B,
+}
+impl<B: BuildCoder> EnvelopeCoderBuilder<B> {
+    /// Makes a new `EnvelopeCoderBuilder` instance.
+    pub fn new(inner: B) -> Self {
+        EnvelopeCoderBuilder { inner }
+    }
+}
+impl<B: BuildCoder> BuildCoder for EnvelopeCoderBuilder<B> {
+    type Coder = EnvelopeCoder<B::Coder>;
+
+    fn build_coder(&self) -> Result<Self::Coder> {
+        let inner = track!(self.inner.build_coder())?;
+        Ok(EnvelopeCoder::new(inner, &self.inner.coder_id()))
+    }
+
+    fn coder_id(&self) -> String {
+        format!("envelope:{}", self.inner.coder_id())
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(bytes);
+    u32::from_le_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::{Failed, MainError};
+
+    use super::*;
+    use crate::reedsolomon::ReedSolomonCoder;
+    use crate::replica::ReplicaCoder;
+    use crate::test_util::{non_zero, test_data};
+
+    #[test]
+    fn it_works() -> Result<(), MainError> {
+        let builder =
+            EnvelopeCoderBuilder::new(track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?);
+        assert_eq!(builder.coder_id(), "envelope:reedsolomon:4:2");
+        let mut coder = track!(builder.build_coder())?;
+
+        let data = test_data(1000);
+        let mut encoded = track!(coder.encode(&data))?;
+        let size = track_assert_some!(coder.fragment_size(data.len()), Failed);
+        assert!(encoded.iter().all(|f| f.len() == size));
+
+        let mut buffers = vec![vec![0; size]; 6];
+        let mut refs = buffers.iter_mut().map(|b| &mut b[..]).collect::<Vec<_>>();
+        track!(coder.encode_into(&data, &mut refs))?;
+        assert_eq!(buffers, encoded);
+
+        let info = track!(coder.fragment_info(&encoded[5]))?;
+        assert_eq!(info.index, Some(5));
+        assert_eq!(info.data_len, Some(1000));
+
+        // Truncated and corrupted fragments are skipped
+        encoded[0].truncate(size - 1);
+        encoded[3][EnvelopeHeader::SIZE + 10] ^= 1;
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        assert_eq!(track!(coder.decode(&fragments))?, data);
+        assert_eq!(track!(coder.reconstruct(3, &fragments))?, buffers[3]);
+        let reconstructed = track!(coder.reconstruct(0, &fragments[1..]))?;
+        assert_eq!(reconstructed, buffers[0]);
+
+        let report = coder.verify(&fragments);
+        assert_eq!(report.corrupt, vec![0, 3]);
+        assert_eq!(report.unverified, vec![1, 2, 4, 5]);
+
+        // Too many corrupted fragments
+        encoded[1][EnvelopeHeader::SIZE] ^= 1;
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        assert!(coder.decode(&fragments).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_and_stale_fragments_are_skipped() -> Result<(), MainError> {
+        let mut coder = track!(EnvelopeCoderBuilder::new(ReplicaCoder::new(
+            non_zero(2)?,
+            non_zero(1)?
+        ))
+        .build_coder())?;
+        let encoded = track!(coder.encode(b"foo"))?;
+        let stale = track!(coder.encode(b"stale data"))?;
+
+        // A zero-length fragment (e.g., an empty file due to a torn write)
+        let fragments = [&[][..], &encoded[1][..], &encoded[2][..]];
+        assert_eq!(track!(coder.decode(&fragments))?, b"foo");
+        assert_eq!(
+            EnvelopeHeader::parse(&[]).map_err(|e| *e.kind()),
+            Err(ErrorKind::CorruptedFragments)
+        );
+
+        // A stale fragment given first does not hide the others
+        let fragments = [&stale[0][..], &encoded[1][..], &encoded[2][..]];
+        assert_eq!(track!(coder.decode(&fragments))?, b"foo");
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_fragments_are_rejected() -> Result<(), MainError> {
+        let (data_fragments, parity_fragments) = (non_zero(2)?, non_zero(1)?);
+        let mut replica = track!(EnvelopeCoderBuilder::new(ReplicaCoder::new(
+            data_fragments,
+            parity_fragments
+        ))
+        .build_coder())?;
+        let mut reedsolomon = track!(EnvelopeCoderBuilder::new(track!(ReedSolomonCoder::new(
+            data_fragments,
+            parity_fragments
+        ))?)
+        .build_coder())?;
+
+        let encoded = track!(reedsolomon.encode(b"foo"))?;
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        assert_eq!(
+            replica.decode(&fragments).map_err(|e| *e.kind()),
+            Err(ErrorKind::CoderMismatch)
+        );
+        assert_eq!(replica.verify(&fragments).unidentified, vec![0, 1, 2]);
+
+        let mut fragment = encoded[0].clone();
+        fragment[4] = VERSION + 1;
+        assert_eq!(
+            EnvelopeHeader::parse(&fragment).map_err(|e| *e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+        fragment[4] = VERSION;
+        fragment[12] ^= 1;
+        assert_eq!(
+            EnvelopeHeader::parse(&fragment).map_err(|e| *e.kind()),
+            Err(ErrorKind::CorruptedFragments)
+        );
+        Ok(())
+    }
+}
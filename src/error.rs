@@ -8,7 +8,10 @@ use trackable::error::TrackableError;
 pub struct Error(TrackableError<ErrorKind>);
 
 /// Possible error kinds.
+///
+/// New kinds may be added in minor releases, so matches on this enum need a wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum ErrorKind {
     /// Failed to decode data due to input fragments corruption.
     CorruptedFragments,
@@ -16,6 +19,18 @@ pub enum ErrorKind {
     /// Input is invalid.
     InvalidInput,
 
+    /// The fragments were produced by a coder different from the one used for decoding
+    /// (e.g., the numbers of data and parity fragments, the backend or the checksum algorithm differ).
+    CoderMismatch,
+
+    /// The pool has too many queued operations to accept a new one.
+    ///
+    /// The operation can be retried after some of the queued operations finish.
+    Overloaded,
+
+    /// The deadline of the operation has passed.
+    Timeout,
+
     /// Other error.
     Other,
 }
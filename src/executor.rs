@@ -0,0 +1,299 @@
+//! Executors that run the tasks of [`ErasureCoderPool`].
+//!
+//! By default, [`ErasureCoderPool`] runs its tasks on the process-wide [`DefaultExecutor`].
+//! [`ThreadPool`] can be used to give a pool its own dedicated threads,
+//! and any other executor can be plugged in by implementing the [`Execute`] trait.
+//!
+//! [`ErasureCoderPool`]: ../struct.ErasureCoderPool.html
+//! [`DefaultExecutor`]: ./struct.DefaultExecutor.html
+//! [`ThreadPool`]: ./struct.ThreadPool.html
+//! [`Execute`]: ./trait.Execute.html
+use fibers_tasque::DefaultCpuTaskQueue;
+use std::collections::VecDeque;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use trackable::error::ErrorKindExt;
+
+use crate::{ErrorKind, Result};
+
+/// A task to be executed by an executor.
+pub type Task = Box<dyn FnOnce() + Send + 'static>;
+
+/// This trait allows for executing tasks on some threads.
+///
+/// Implementations must not execute the given task on the calling thread,
+/// because it is usually a scheduler thread of [fibers] and the task may be CPU intensive.
+///
+/// [fibers]: https://crates.io/crates/fibers
+pub trait Execute: Send + Sync + 'static {
+    /// Executes the given task asynchronously.
+    fn execute(&self, task: Task);
+}
+impl<T: Execute + ?Sized> Execute for Arc<T> {
+    fn execute(&self, task: Task) {
+        (**self).execute(task)
+    }
+}
+
+/// An executor that uses [`fibers_tasque::DefaultCpuTaskQueue`].
+///
+/// Note that the queue is shared by the whole process.
+///
+/// [`fibers_tasque::DefaultCpuTaskQueue`]: https://docs.rs/fibers_tasque/0.1/fibers_tasque/struct.DefaultCpuTaskQueue.html
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultExecutor;
+impl Execute for DefaultExecutor {
+    fn execute(&self, task: Task) {
+        DefaultCpuTaskQueue.with(|queue| queue.enqueue(task));
+    }
+}
+
+/// [`ThreadPool`] builder.
+///
+/// [`ThreadPool`]: ./struct.ThreadPool.html
+#[derive(Debug, Clone)]
+pub struct ThreadPoolBuilder {
+    threads: NonZeroUsize,
+    name: String,
+    stack_size: Option<usize>,
+}
+impl ThreadPoolBuilder {
+    /// The default name of the threads.
+    pub const DEFAULT_NAME: &'static str = "ecpool";
+
+    /// Makes a new `ThreadPoolBuilder` with the default settings.
+    ///
+    /// The default number of threads is the available parallelism of the machine.
+    pub fn new() -> Self {
+        ThreadPoolBuilder {
+            threads: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            name: Self::DEFAULT_NAME.to_owned(),
+            stack_size: None,
+        }
+    }
+
+    /// Sets the number of threads in the pool.
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets the name of the threads.
+    ///
+    /// The threads are named `"{name}-{i}"` where `i` is the index of each thread.
+    ///
+    /// The default value is `DEFAULT_NAME`.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    /// Sets the stack size of the threads in bytes.
+    ///
+    /// If omitted, the default stack size of the standard library is used.
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Spawns the threads and returns the resulting `ThreadPool`.
+    pub fn build(&self) -> Result<ThreadPool> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                tasks: VecDeque::new(),
+                shutdown: false,
+            }),
+            condvar: Condvar::new(),
+        });
+        let handle = Handle {
+            shared: Arc::clone(&shared),
+            threads: self.threads,
+        };
+        for i in 0..self.threads.get() {
+            let mut builder = thread::Builder::new().name(format!("{}-{}", self.name, i));
+            if let Some(size) = self.stack_size {
+                builder = builder.stack_size(size);
+            }
+            let shared = Arc::clone(&shared);
+            track!(builder
+                .spawn(move || shared.run())
+                .map_err(|e| ErrorKind::Other.cause(e)))?;
+        }
+        Ok(ThreadPool {
+            handle: Arc::new(handle),
+        })
+    }
+}
+impl Default for ThreadPoolBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An executor that owns a fixed number of named threads.
+///
+/// The threads exit after all the handles of the pool are dropped and the queued tasks are executed.
+/// If a task panics, the panic is caught and the thread continues to execute the subsequent tasks.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate ecpool;
+/// # extern crate fibers_global;
+/// use ecpool::executor::ThreadPoolBuilder;
+/// use ecpool::replica::ReplicaCoder;
+/// use ecpool::ErasureCoderPool;
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let threads = ThreadPoolBuilder::new()
+///     .threads(NonZeroUsize::new(2).ok_or("invalid input")?)
+///     .name("erasure-coding")
+///     .build()?;
+///
+/// let data_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let parity_fragments = NonZeroUsize::new(1).ok_or("invalid input")?;
+/// let coder = ReplicaCoder::new(data_fragments, parity_fragments);
+/// let pool = ErasureCoderPool::with_executor(coder, threads);
+///
+/// let encoded = fibers_global::execute(pool.encode(vec![0, 1, 2, 3]))?;
+/// assert_eq!(encoded.len(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ThreadPool {
+    handle: Arc<Handle>,
+}
+impl ThreadPool {
+    /// Makes a new `ThreadPool` with the default settings.
+    ///
+    /// This is equivalent to `ThreadPoolBuilder::new().build()`.
+    pub fn new() -> Result<Self> {
+        track!(ThreadPoolBuilder::new().build())
+    }
+
+    /// Returns the number of threads in the pool.
+    pub fn threads(&self) -> NonZeroUsize {
+        self.handle.threads
+    }
+
+    /// Returns the number of the tasks waiting to be executed.
+    pub fn queue_len(&self) -> usize {
+        self.handle.shared.lock().tasks.len()
+    }
+}
+impl Execute for ThreadPool {
+    fn execute(&self, task: Task) {
+        self.handle.shared.lock().tasks.push_back(task);
+        self.handle.shared.condvar.notify_one();
+    }
+}
+
+/// Notifies the threads of the shutdown when dropped.
+#[derive(Debug)]
+struct Handle {
+    shared: Arc<Shared>,
+    threads: NonZeroUsize,
+}
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.condvar.notify_all();
+    }
+}
+
+struct State {
+    tasks: VecDeque<Task>,
+    shutdown: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // Tasks never run while holding the lock, so the lock cannot be poisoned by them.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn run(&self) {
+        while let Some(task) = self.next_task() {
+            let _ = panic::catch_unwind(AssertUnwindSafe(task));
+        }
+    }
+
+    fn next_task(&self) -> Option<Task> {
+        let mut state = self.lock();
+        loop {
+            if let Some(task) = state.tasks.pop_front() {
+                return Some(task);
+            }
+            if state.shutdown {
+                return None;
+            }
+            state = self.condvar.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+impl fmt::Debug for Shared {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Shared")
+            .field("queue_len", &state.tasks.len())
+            .field("shutdown", &state.shutdown)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use trackable::error::{Failed, MainError};
+
+    use super::*;
+    use crate::test_util::non_zero;
+
+    #[test]
+    fn thread_pool_works() -> Result<(), MainError> {
+        let threads = non_zero(2)?;
+        let pool = track!(ThreadPoolBuilder::new()
+            .threads(threads)
+            .name("foo")
+            .build())?;
+        assert_eq!(pool.threads(), threads);
+
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..10 {
+            let tx = tx.clone();
+            pool.execute(Box::new(move || {
+                let name = thread::current().name().map(|n| n.to_owned());
+                let _ = tx.send(name);
+            }));
+        }
+        for _ in 0..10 {
+            let name = track_any_err!(rx.recv_timeout(Duration::from_secs(10)))?;
+            let name = track_assert_some!(name, Failed);
+            assert!(name == "foo-0" || name == "foo-1", "name={}", name);
+        }
+
+        // Panics do not kill the threads
+        for _ in 0..4 {
+            pool.execute(Box::new(|| panic!()));
+        }
+        pool.execute(Box::new(move || {
+            let _ = tx.send(None);
+        }));
+        assert_eq!(
+            track_any_err!(rx.recv_timeout(Duration::from_secs(10)))?,
+            None
+        );
+        Ok(())
+    }
+}
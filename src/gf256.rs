@@ -0,0 +1,182 @@
+//! Arithmetic over GF(2^8) (primitive polynomial `x^8 + x^4 + x^3 + x^2 + 1`).
+const POLYNOMIAL: usize = 0x11d;
+
+struct Tables {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+static TABLES: Tables = make_tables();
+
+const fn make_tables() -> Tables {
+    let mut exp = [0; 512];
+    let mut log = [0; 256];
+    let mut x = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        exp[i + 255] = x as u8;
+        log[x] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= POLYNOMIAL;
+        }
+        i += 1;
+    }
+    Tables { exp, log }
+}
+
+pub fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        TABLES.exp[TABLES.log[a as usize] as usize + TABLES.log[b as usize] as usize]
+    }
+}
+
+/// Returns the multiplicative inverse of `a`.
+///
+/// # Panics
+///
+/// Panics if `a` is zero.
+pub fn inv(a: u8) -> u8 {
+    assert_ne!(a, 0, "Zero has no inverse");
+    TABLES.exp[255 - TABLES.log[a as usize] as usize]
+}
+
+/// Computes `output[i] ^= c * input[i]` for all `i < min(input.len(), output.len())`.
+pub fn mul_slice_xor(c: u8, input: &[u8], output: &mut [u8]) {
+    match c {
+        0 => {}
+        1 => {
+            for (o, i) in output.iter_mut().zip(input) {
+                *o ^= *i;
+            }
+        }
+        _ => {
+            let table = mul_table(c);
+            for (o, i) in output.iter_mut().zip(input) {
+                *o ^= table[*i as usize];
+            }
+        }
+    }
+}
+
+fn mul_table(c: u8) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (x, t) in table.iter_mut().enumerate() {
+        *t = mul(c, x as u8);
+    }
+    table
+}
+
+/// Returns a `rows x cols` Cauchy matrix.
+///
+/// Every square sub-matrix of the result is invertible, so stacking it under the identity matrix
+/// yields a systematic MDS generator matrix.
+/// It is required that `rows + cols <= 256`.
+pub fn cauchy_matrix(rows: usize, cols: usize) -> Vec<Vec<u8>> {
+    (0..rows)
+        .map(|r| (0..cols).map(|c| inv(((cols + r) ^ c) as u8)).collect())
+        .collect()
+}
+
+/// Inverts the given square matrix by using Gauss-Jordan elimination.
+///
+/// Returns `None` if the matrix is singular.
+pub fn invert_matrix(matrix: &[Vec<u8>]) -> Option<Vec<Vec<u8>>> {
+    let n = matrix.len();
+    let mut work = matrix.to_vec();
+    let mut inverse = (0..n)
+        .map(|r| (0..n).map(|c| (r == c) as u8).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    for col in 0..n {
+        let pivot = (col..n).find(|&r| work[r][col] != 0)?;
+        work.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let scale = inv(work[col][col]);
+        for c in 0..n {
+            work[col][c] = mul(work[col][c], scale);
+            inverse[col][c] = mul(inverse[col][c], scale);
+        }
+        for r in 0..n {
+            let factor = work[r][col];
+            if r == col || factor == 0 {
+                continue;
+            }
+            for c in 0..n {
+                work[r][c] ^= mul(factor, work[col][c]);
+                inverse[r][c] ^= mul(factor, inverse[col][c]);
+            }
+        }
+    }
+    Some(inverse)
+}
+
+/// Selects `needed` linearly independent rows from the given ones (in order of preference).
+///
+/// Returns the positions of the selected rows, or `None` if the rank of `rows` is less than `needed`.
+pub fn select_independent_rows(rows: &[Vec<u8>], needed: usize) -> Option<Vec<usize>> {
+    let mut basis: Vec<(usize, Vec<u8>)> = Vec::with_capacity(needed);
+    let mut selected = Vec::with_capacity(needed);
+    for (i, row) in rows.iter().enumerate() {
+        if selected.len() == needed {
+            break;
+        }
+        let mut row = row.clone();
+        for (pivot, b) in &basis {
+            let factor = row[*pivot];
+            if factor != 0 {
+                for (r, &x) in row.iter_mut().zip(b.iter()) {
+                    *r ^= mul(factor, x);
+                }
+            }
+        }
+        if let Some(pivot) = row.iter().position(|&x| x != 0) {
+            let scale = inv(row[pivot]);
+            for r in row.iter_mut() {
+                *r = mul(*r, scale);
+            }
+            basis.push((pivot, row));
+            selected.push(i);
+        }
+    }
+    if selected.len() == needed {
+        Some(selected)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::{Failed, MainError};
+
+    use super::*;
+
+    #[test]
+    fn field_axioms_hold() {
+        for a in 1..=255u8 {
+            assert_eq!(mul(a, inv(a)), 1);
+        }
+        assert_eq!(mul(0x80, 2), 0x1d);
+    }
+
+    #[test]
+    fn cauchy_submatrices_are_invertible() -> Result<(), MainError> {
+        let matrix = cauchy_matrix(4, 4);
+        let inverse = track_assert_some!(invert_matrix(&matrix), Failed);
+        for (r, row) in matrix.iter().enumerate() {
+            for c in 0..4 {
+                let v = row
+                    .iter()
+                    .zip(inverse.iter())
+                    .fold(0, |acc, (&a, b)| acc ^ mul(a, b[c]));
+                assert_eq!(v, (r == c) as u8);
+            }
+        }
+        Ok(())
+    }
+}
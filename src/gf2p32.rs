@@ -0,0 +1,148 @@
+//! Arithmetic over GF(2^32) (primitive polynomial `x^32 + x^22 + x^2 + x + 1`).
+//!
+//! This is the field used by [jerasure] (via [gf-complete]) when `w = 32`.
+//! Regions are regarded as sequences of little-endian 32-bit words.
+//!
+//! [jerasure]: https://github.com/ceph/jerasure
+//! [gf-complete]: https://github.com/ceph/gf-complete
+const POLYNOMIAL: u64 = 0x1_0040_0007;
+
+pub fn mul(a: u32, b: u32) -> u32 {
+    let mut product = 0u64;
+    let mut a = u64::from(a);
+    let mut b = b;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a <<= 1;
+        b >>= 1;
+    }
+    for i in (32..64).rev() {
+        if product & (1 << i) != 0 {
+            product ^= POLYNOMIAL << (i - 32);
+        }
+    }
+    product as u32
+}
+
+/// Returns the multiplicative inverse of `a` (i.e., `a^(2^32 - 2)`).
+///
+/// # Panics
+///
+/// Panics if `a` is zero.
+pub fn inv(a: u32) -> u32 {
+    assert_ne!(a, 0, "Zero has no inverse");
+    let mut result = 1;
+    let mut base = a;
+    let mut exp = u32::MAX - 1;
+    while exp != 0 {
+        if exp & 1 != 0 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+pub fn div(a: u32, b: u32) -> u32 {
+    mul(a, inv(b))
+}
+
+/// Computes `output[i] ^= c * input[i]` word by word.
+///
+/// The lengths of `input` and `output` must be multiples of four.
+pub fn mul_slice_xor(c: u32, input: &[u8], output: &mut [u8]) {
+    match c {
+        0 => {}
+        1 => {
+            for (o, i) in output.iter_mut().zip(input) {
+                *o ^= *i;
+            }
+        }
+        _ => {
+            let tables = mul_tables(c);
+            for (o, i) in output.chunks_exact_mut(4).zip(input.chunks_exact(4)) {
+                let i = u32::from_le_bytes([i[0], i[1], i[2], i[3]]);
+                let product = tables[0][(i & 0xFF) as usize]
+                    ^ tables[1][((i >> 8) & 0xFF) as usize]
+                    ^ tables[2][((i >> 16) & 0xFF) as usize]
+                    ^ tables[3][(i >> 24) as usize];
+                let o_word = u32::from_le_bytes([o[0], o[1], o[2], o[3]]) ^ product;
+                o.copy_from_slice(&o_word.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Makes the tables of which `tables[j][x]` is `c * (x << (8 * j))`.
+fn mul_tables(c: u32) -> Vec<[u32; 256]> {
+    let mut basis = [0; 32];
+    let mut x = c;
+    for b in basis.iter_mut() {
+        *b = x;
+        x = mul(x, 2);
+    }
+    (0..4)
+        .map(|j| {
+            let mut table = [0; 256];
+            for x in 1..256usize {
+                let low = x.trailing_zeros() as usize;
+                table[x] = table[x & (x - 1)] ^ basis[8 * j + low];
+            }
+            table
+        })
+        .collect()
+}
+
+/// Inverts the given square matrix by using Gauss-Jordan elimination.
+///
+/// Returns `None` if the matrix is singular.
+pub fn invert_matrix(matrix: &[Vec<u32>]) -> Option<Vec<Vec<u32>>> {
+    let n = matrix.len();
+    let mut work = matrix.to_vec();
+    let mut inverse = (0..n)
+        .map(|r| (0..n).map(|c| (r == c) as u32).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    for col in 0..n {
+        let pivot = (col..n).find(|&r| work[r][col] != 0)?;
+        work.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let scale = inv(work[col][col]);
+        for c in 0..n {
+            work[col][c] = mul(work[col][c], scale);
+            inverse[col][c] = mul(inverse[col][c], scale);
+        }
+        for r in 0..n {
+            let factor = work[r][col];
+            if r == col || factor == 0 {
+                continue;
+            }
+            for c in 0..n {
+                work[r][c] ^= mul(factor, work[col][c]);
+                inverse[r][c] ^= mul(factor, inverse[col][c]);
+            }
+        }
+    }
+    Some(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_axioms_hold() {
+        for &a in &[1, 2, 3, 0x1234_5678, 0x8000_0000, u32::MAX] {
+            assert_eq!(mul(a, inv(a)), 1);
+        }
+        assert_eq!(mul(0x8000_0000, 2), 0x0040_0007);
+
+        let input = [0x78, 0x56, 0x34, 0x12];
+        let mut output = [0; 4];
+        mul_slice_xor(0xdead_beef, &input, &mut output);
+        assert_eq!(u32::from_le_bytes(output), mul(0xdead_beef, 0x1234_5678));
+    }
+}
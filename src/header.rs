@@ -0,0 +1,260 @@
+//! The header prepended to the fragments produced by the pure-Rust coders of this crate.
+use std::ops::Range;
+
+use crate::verify::FragmentMeta;
+use crate::{ErrorKind, Fragment, FragmentInfo, Result};
+
+/// A fragment header.
+///
+/// Layout (little-endian):
+/// - `0..4`: index of the fragment
+/// - `4..12`: size of the original data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentHeader {
+    pub index: usize,
+    pub data_len: usize,
+}
+impl FragmentHeader {
+    pub const SIZE: usize = 12;
+
+    pub fn new(index: usize, data_len: usize) -> Self {
+        FragmentHeader { index, data_len }
+    }
+
+    pub fn read_from(fragment: &Fragment) -> Result<Self> {
+        track_assert!(
+            fragment.len() >= Self::SIZE,
+            ErrorKind::CorruptedFragments,
+            "Too short fragment: len={}",
+            fragment.len()
+        );
+        let mut index = [0; 4];
+        let mut data_len = [0; 8];
+        index.copy_from_slice(&fragment[0..4]);
+        data_len.copy_from_slice(&fragment[4..12]);
+        Ok(FragmentHeader {
+            index: u32::from_le_bytes(index) as usize,
+            data_len: u64::from_le_bytes(data_len) as usize,
+        })
+    }
+
+    pub fn write_to(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&(self.index as u32).to_le_bytes());
+        buf[4..12].copy_from_slice(&(self.data_len as u64).to_le_bytes());
+    }
+}
+
+/// Returns the metadata of the given fragment produced by the coder named `backend`.
+pub fn fragment_info(fragment: &Fragment, backend: &str) -> Result<FragmentInfo> {
+    let header = track!(FragmentHeader::read_from(fragment))?;
+    Ok(FragmentInfo {
+        index: Some(header.index),
+        data_len: Some(header.data_len),
+        payload_len: fragment.len() - FragmentHeader::SIZE,
+        checksum: None,
+        backend: backend.to_owned(),
+    })
+}
+
+/// Parses the header of the given fragment for verification.
+pub fn fragment_meta(fragment: &Fragment) -> Option<FragmentMeta> {
+    FragmentHeader::read_from(fragment)
+        .ok()
+        .map(|header| FragmentMeta {
+            index: header.index,
+            data_len: header.data_len,
+            intact: true,
+        })
+}
+
+/// Writes the data fragments of the given data to `buffers`.
+///
+/// The `i`-th buffer holds the header and the `payload_len` bytes of the data starting from `i * payload_len`
+/// (the remaining bytes are filled with zeros).
+pub fn write_data_fragments(data: &[u8], payload_len: usize, buffers: &mut [&mut [u8]]) {
+    for (i, buffer) in buffers.iter_mut().enumerate() {
+        FragmentHeader::new(i, data.len()).write_to(buffer);
+        let start = std::cmp::min(i * payload_len, data.len());
+        let end = std::cmp::min(start + payload_len, data.len());
+        let payload = &mut buffer[FragmentHeader::SIZE..];
+        payload[..end - start].copy_from_slice(&data[start..end]);
+        payload[end - start..].iter_mut().for_each(|b| *b = 0);
+    }
+}
+
+/// Validates the arguments of `ErasureCode::update_parity` and
+/// returns the difference between the payloads of `old_data` and `new_data`.
+pub fn parity_delta(
+    data_index: usize,
+    old_data: &Fragment,
+    new_data: &Fragment,
+    parities: &[&Fragment],
+    data_fragments: usize,
+) -> Result<Vec<u8>> {
+    track_assert!(
+        data_index < data_fragments,
+        ErrorKind::InvalidInput,
+        "Not a data fragment: data_index={}, data_fragments={}",
+        data_index,
+        data_fragments
+    );
+    let header = track!(FragmentHeader::read_from(old_data))?;
+    track_assert_eq!(
+        header.index,
+        data_index,
+        ErrorKind::InvalidInput,
+        "Unexpected fragment index"
+    );
+    track_assert_eq!(
+        track!(FragmentHeader::read_from(new_data))?,
+        header,
+        ErrorKind::InvalidInput,
+        "Fragment header mismatch"
+    );
+    track_assert_eq!(
+        new_data.len(),
+        old_data.len(),
+        ErrorKind::InvalidInput,
+        "Fragment size mismatch"
+    );
+    for (i, parity) in parities.iter().enumerate() {
+        track_assert_eq!(
+            track!(FragmentHeader::read_from(parity))?,
+            FragmentHeader::new(data_fragments + i, header.data_len),
+            ErrorKind::InvalidInput,
+            "Unexpected parity fragment: i={}",
+            i
+        );
+        track_assert_eq!(
+            parity.len(),
+            old_data.len(),
+            ErrorKind::InvalidInput,
+            "Fragment size mismatch: index={}",
+            data_fragments + i
+        );
+    }
+    Ok(old_data[FragmentHeader::SIZE..]
+        .iter()
+        .zip(new_data[FragmentHeader::SIZE..].iter())
+        .map(|(a, b)| a ^ b)
+        .collect())
+}
+
+/// Payloads of fragments arranged by their indices.
+#[derive(Debug)]
+pub struct Payloads<'a> {
+    pub data_len: usize,
+    pub payload_len: usize,
+    pub slots: Vec<Option<&'a [u8]>>,
+}
+impl<'a> Payloads<'a> {
+    /// Parses the headers of the given fragments and arranges those payloads by their indices.
+    ///
+    /// Fragments that have the same index as a preceding one are ignored.
+    pub fn from_fragments(fragments: &[&'a Fragment], total: usize) -> Result<Self> {
+        track!(Self::arrange(fragments.iter().map(|f| (None, *f)), total))
+    }
+
+    /// Arranges the payloads of the given fragments by the indices specified by the caller.
+    ///
+    /// The indices recorded in the headers are ignored.
+    pub fn from_indexed_fragments(
+        fragments: &[(usize, &'a Fragment)],
+        total: usize,
+    ) -> Result<Self> {
+        for &(index, _) in fragments {
+            track_assert!(
+                index < total,
+                ErrorKind::InvalidInput,
+                "Too large index: index={}, fragments={}",
+                index,
+                total
+            );
+        }
+        track!(Self::arrange(
+            fragments.iter().map(|&(i, f)| (Some(i), f)),
+            total
+        ))
+    }
+
+    fn arrange<I>(fragments: I, total: usize) -> Result<Self>
+    where
+        I: Iterator<Item = (Option<usize>, &'a Fragment)>,
+    {
+        let mut payloads: Option<Payloads> = None;
+        for (index, fragment) in fragments {
+            let mut header = track!(FragmentHeader::read_from(fragment))?;
+            if let Some(index) = index {
+                header.index = index;
+            }
+            let payload = &fragment[FragmentHeader::SIZE..];
+            track_assert!(
+                header.index < total,
+                ErrorKind::CorruptedFragments,
+                "Too large index: expected_fragments={}, found_index={}",
+                total,
+                header.index
+            );
+            let p = payloads.get_or_insert_with(|| Payloads {
+                data_len: header.data_len,
+                payload_len: payload.len(),
+                slots: vec![None; total],
+            });
+            track_assert_eq!(
+                header.data_len,
+                p.data_len,
+                ErrorKind::CorruptedFragments,
+                "Inconsistent data size: index={}",
+                header.index
+            );
+            track_assert_eq!(
+                payload.len(),
+                p.payload_len,
+                ErrorKind::CorruptedFragments,
+                "Inconsistent fragment size: index={}",
+                header.index
+            );
+            if p.slots[header.index].is_none() {
+                p.slots[header.index] = Some(payload);
+            }
+        }
+        let payloads = track_assert_some!(payloads, ErrorKind::InvalidInput, "No fragments");
+        Ok(payloads)
+    }
+
+    /// Checks that the size of the payloads is `expected` that is computed by the coder identified by `coder_id`.
+    ///
+    /// The headers do not identify the coders, so the difference is reported as corrupted fragments
+    /// (it is usually caused by a different number of data fragments).
+    pub fn check_payload_len(&self, coder_id: &str, expected: usize) -> Result<()> {
+        track_assert_eq!(
+            self.payload_len,
+            expected,
+            ErrorKind::CorruptedFragments,
+            "Unexpected fragment size: coder={}, data_len={}, expected_payload_len={}, found_payload_len={}",
+            coder_id,
+            self.data_len,
+            expected,
+            self.payload_len
+        );
+        Ok(())
+    }
+
+    /// Returns the number of available (distinct) fragments.
+    pub fn available(&self) -> usize {
+        self.slots.iter().filter(|s| s.is_some()).count()
+    }
+
+    /// Returns the view of the same range of the payloads.
+    pub fn slice(&self, range: Range<usize>) -> Payloads<'a> {
+        Payloads {
+            data_len: self.data_len,
+            payload_len: range.len(),
+            slots: self
+                .slots
+                .iter()
+                .map(|s| s.map(|p| &p[range.clone()]))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+/// The metadata of a fragment that can be obtained without decoding.
+///
+/// See [`ErasureCode::fragment_info`] for details.
+///
+/// [`ErasureCode::fragment_info`]: ./trait.ErasureCode.html#method.fragment_info
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FragmentInfo {
+    /// The index of the fragment.
+    ///
+    /// `None` if the fragment does not record it.
+    pub index: Option<usize>,
+
+    /// The size of the original data.
+    ///
+    /// `None` if the fragment does not record it.
+    pub data_len: Option<usize>,
+
+    /// The size of the payload (i.e., the fragment without its header).
+    pub payload_len: usize,
+
+    /// The name of the checksum algorithm of the payload (e.g., `"crc32"`).
+    ///
+    /// `None` if the fragment has no checksum.
+    pub checksum: Option<String>,
+
+    /// The name of the backend that produced the fragment (e.g., `"jerasure_rs_vand"`).
+    pub backend: String,
+}
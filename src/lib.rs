@@ -4,13 +4,44 @@
 //! and of which implemtations can be executed via [`ErasureCoderPool`].
 //!
 //! There are some built-in implementations of the trait:
+//! - [`ClayCoder`]:
+//!   - This implementation is Clay codes (minimum storage regenerating codes) written in pure Rust.
+//!   - A lost fragment can be reconstructed by reading only a part of each of the other fragments.
 //! - [`LibErasureCoder`]:
 //!   - This implementation uses [`liberasurecode`] crate that is a wrapper for [openstack/liberasurecode] library.
 //!   - It is highly optimized and stable but only available in Unix environments.
+//! - [`LrcCoder`]:
+//!   - This implementation is Local Reconstruction Codes written in pure Rust.
+//!   - A lost fragment can be reconstructed by reading only the fragments in the same local group.
+//! - [`PureLibErasureCoder`]:
+//!   - This implementation is written in pure Rust and byte-compatible with [`LibErasureCoder`]
+//!     using `jerasure_rs_vand` backend.
+//!   - It is useful for decoding fragments produced by [`LibErasureCoder`] on hosts where
+//!     [openstack/liberasurecode] is unavailable.
+//! - [`ReedSolomonCoder`]:
+//!   - This implementation is a systematic Reed-Solomon coder written in pure Rust.
+//!   - It does not depend on any native libraries, so it is available on any targets.
 //! - [`ReplicaCoder`]:
 //!   - This implementation simply replicates the input data.
 //!   - It is provided for example and testing purposes only and not intended to use in production.
 //!
+//! The fragments produced by any of those can be wrapped in self-describing envelopes by using [`EnvelopeCoder`].
+//!
+//! The coders can also be built from textual specifications (e.g., `"reedsolomon:4:2"`)
+//! by using [`CoderRegistry`].
+//!
+//! By default, [`ErasureCoderPool`] shares the process-wide CPU task queue of `fibers_tasque`.
+//! A dedicated [`ThreadPool`] (or any [`Execute`] implementation) can be given to it
+//! via [`ErasureCoderPool::with_executor`].
+//! [`ErasureCoderPoolBuilder`] can also limit the operations queued in the pool,
+//! so that the callers can shed load (`ErrorKind::Overloaded`) instead of queueing unbounded work.
+//! Maintenance operations (e.g., repair) can be given a lower [`Priority`] via [`ErasureCoderPool::with_priority`]
+//! so that they do not slow down the foreground ones.
+//! Operations can also be given deadlines via [`ErasureCoderPool::with_deadline`],
+//! after which the queued ones are discarded without executing (`ErrorKind::Timeout`).
+//! The [`metrics`] of the pool (e.g., queue depth and latency histograms) can be exported
+//! in the Prometheus text exposition format.
+//!
 //!
 //! # Build Prerequisites
 //!
@@ -22,6 +53,26 @@
 //! $ cd liberasurecode && sudo ./install_deps.sh
 //! ```
 //!
+//! [`LibErasureCoder`] can be excluded by disabling the default `liberasurecode` feature.
+//! In that case, no native libraries are required to build this crate:
+//!
+//! ```toml
+//! [dependencies]
+//! ecpool = { version = "1", default-features = false }
+//! ```
+//!
+//! # Optional Features
+//!
+//! - `bytes`:
+//!   - Enables [`ErasureCoderPool::encode_bytes`] that returns the fragments as [`Bytes`] slices of
+//!     a single contiguous allocation.
+//!   - Since [`Bytes`] implements `AsRef<[u8]>`, those can be passed to the decoding methods of the pool as is.
+//! - `serde`:
+//!   - Implements `Serialize` and `Deserialize` for the coder builders (e.g., [`LibErasureCoderBuilder`])
+//!     and [`CoderSpec`].
+//!   - The builders are serialized as maps that have a `version` field and the parameters of the coders,
+//!     so that the persisted configurations remain readable by later releases.
+//!
 //! # Examples
 //!
 //! Basic usage:
@@ -74,33 +125,84 @@
 //!
 //! [`ErasureCoderPool`]: ./struct.ErasureCoderPool.html
 //! [`ErasureCode`]: ./trait.ErasureCode.html
+//! [`ErasureCoderPool::encode_bytes`]: ./struct.ErasureCoderPool.html#method.encode_bytes
+//! [`Bytes`]: https://docs.rs/bytes/1/bytes/struct.Bytes.html
 //! [`liberasurecode`]: https://github.com/frugalos/liberasurecode
 //! [openstack/liberasurecode]: https://github.com/openstack/liberasurecode
+//! [`ClayCoder`]: ./clay/struct.ClayCoder.html
 //! [`LibErasureCoder`]: ./liberasurecode/struct.LibErasureCoder.html
+//! [`LrcCoder`]: ./lrc/struct.LrcCoder.html
+//! [`PureLibErasureCoder`]: ./pure_liberasurecode/struct.PureLibErasureCoder.html
+//! [`ReedSolomonCoder`]: ./reedsolomon/struct.ReedSolomonCoder.html
 //! [`ReplicaCoder`]: ./replica/struct.ReplicaCoder.html
+//! [`CoderRegistry`]: ./spec/struct.CoderRegistry.html
+//! [`CoderSpec`]: ./spec/struct.CoderSpec.html
+//! [`EnvelopeCoder`]: ./envelope/struct.EnvelopeCoder.html
+//! [`LibErasureCoderBuilder`]: ./liberasurecode/struct.LibErasureCoderBuilder.html
+//! [`ThreadPool`]: ./executor/struct.ThreadPool.html
+//! [`Execute`]: ./executor/trait.Execute.html
+//! [`ErasureCoderPool::with_executor`]: ./struct.ErasureCoderPool.html#method.with_executor
+//! [`ErasureCoderPoolBuilder`]: ./struct.ErasureCoderPoolBuilder.html
+//! [`Priority`]: ./enum.Priority.html
+//! [`ErasureCoderPool::with_priority`]: ./struct.ErasureCoderPool.html#method.with_priority
+//! [`ErasureCoderPool::with_deadline`]: ./struct.ErasureCoderPool.html#method.with_deadline
+//! [`metrics`]: ./metrics/index.html
 #![warn(missing_docs)]
+#[cfg(feature = "bytes")]
+extern crate bytes;
 extern crate fibers;
 #[cfg(test)]
 extern crate fibers_global;
 extern crate fibers_tasque;
 extern crate futures;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 #[macro_use]
 extern crate trackable;
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "liberasurecode"))]
 extern crate liberasurecode as libec;
 
 use std::num::NonZeroUsize;
+use std::ops::Range;
 
 pub use crate::error::{Error, ErrorKind};
-pub use crate::pool::ErasureCoderPool;
+pub use crate::info::FragmentInfo;
+pub use crate::plan::FragmentRange;
+pub use crate::pool::{ErasureCoderPool, ErasureCoderPoolBuilder};
+pub use crate::scheduler::Priority;
+pub use crate::verify::VerifyReport;
 
-#[cfg(unix)]
+pub mod clay;
+pub mod envelope;
+pub mod executor;
+#[cfg(all(unix, feature = "liberasurecode"))]
 pub mod liberasurecode;
+pub mod lrc;
+pub mod metrics;
+pub mod pure_liberasurecode;
+pub mod reedsolomon;
 pub mod replica;
+pub mod spec;
+pub mod stripe;
 
+mod crc32;
 mod error;
+mod gf256;
+mod gf2p32;
+mod header;
+mod info;
+mod limiter;
+mod plan;
 mod pool;
+mod scheduler;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(test)]
+mod test_util;
+mod verify;
 
 /// This crate specific [`Result`] type.
 ///
@@ -134,11 +236,135 @@ pub trait ErasureCode {
     /// (where `N = self.data_fragments()` and `M = self.parity_fragments()`).
     fn encode(&mut self, data: &[u8]) -> Result<Vec<FragmentBuf>>;
 
+    /// Returns the size of each fragment that will be produced by encoding data of `data_len` bytes.
+    ///
+    /// `None` is returned if the implementation cannot tell the size without encoding (the default).
+    fn fragment_size(&self, data_len: usize) -> Option<usize> {
+        let _ = data_len;
+        None
+    }
+
+    /// Encodes the given data into the given buffers.
+    ///
+    /// `buffers` must consist of `N + M` buffers
+    /// (where `N = self.data_fragments()` and `M = self.parity_fragments()`) and
+    /// the size of each buffer must be equal to the size of the corresponding fragment
+    /// (i.e., [`fragment_size`] if it is known).
+    /// Otherwise, this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// The default implementation calls [`encode`] and copies the resulting fragments to the buffers.
+    ///
+    /// [`fragment_size`]: #method.fragment_size
+    /// [`encode`]: #tymethod.encode
+    fn encode_into(&mut self, data: &[u8], buffers: &mut [&mut [u8]]) -> Result<()> {
+        track!(check_buffers(buffers, self.fragments().get(), None))?;
+        let fragments = self.encode(data)?;
+        for (buffer, fragment) in buffers.iter_mut().zip(fragments.iter()) {
+            track_assert_eq!(
+                buffer.len(),
+                fragment.len(),
+                ErrorKind::InvalidInput,
+                "Unexpected buffer size"
+            );
+        }
+        for (buffer, fragment) in buffers.iter_mut().zip(fragments.iter()) {
+            buffer.copy_from_slice(fragment);
+        }
+        Ok(())
+    }
+
+    /// Computes the parity fragments that correspond to the data
+    /// in which the data fragment specified by `data_index` is changed from `old_data` to `new_data`.
+    ///
+    /// `parities` must be the current `M` parity fragments in the order of their indices
+    /// (where `M = self.parity_fragments()`), and
+    /// `old_data` and `new_data` must have the same size (i.e., the size of the original data is unchanged).
+    /// The result vector contains the updated parity fragments in the same order as `parities`.
+    ///
+    /// Unlike [`encode`], this only reads the changed data fragment and the parity fragments.
+    ///
+    /// The default implementation returns an `ErrorKind::Other` error since it is only possible for
+    /// the implementations that know how the parity fragments are computed.
+    ///
+    /// [`encode`]: #tymethod.encode
+    fn update_parity(
+        &mut self,
+        data_index: usize,
+        old_data: &Fragment,
+        new_data: &Fragment,
+        parities: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        let _ = (data_index, old_data, new_data, parities);
+        track_panic!(
+            ErrorKind::Other,
+            "Incremental parity update is not supported by this implementation"
+        )
+    }
+
     /// Decodes the original data from the given fragments.
     ///
     /// Note whether the correctness of the result data has been validated depends on the implementations.
+    ///
+    /// If the metadata of the fragments show that those were produced by a different coder
+    /// (e.g., the backend of liberasurecode fragments differs), this will return an `ErrorKind::CoderMismatch` error.
+    /// The coders whose fragment headers do not identify the coders (e.g., `ReedSolomonCoder`)
+    /// report out-of-range or inconsistent metadata as `ErrorKind::CorruptedFragments` instead.
     fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>>;
 
+    /// Decodes the given range of the original data from the given fragments.
+    ///
+    /// If `range` is out of the original data, this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// The default implementation decodes the whole data and then extracts the range.
+    fn decode_range(&mut self, range: Range<usize>, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let data = self.decode(fragments)?;
+        track!(plan::check_data_range(&range, data.len()))?;
+        Ok(data[range].to_vec())
+    }
+
+    /// Returns the byte ranges of the fragments that hold the given range of the original data
+    /// as is (i.e., without decoding).
+    ///
+    /// `data_len` is the size of the original data.
+    /// If all of the returned ranges are available, [`decode_range`] does not need any other fragments.
+    ///
+    /// `None` is returned if `range` is out of `0..data_len` or
+    /// the implementation does not store the original data in that way (the default).
+    ///
+    /// [`decode_range`]: #method.decode_range
+    fn fragment_ranges(&self, range: Range<usize>, data_len: usize) -> Option<Vec<FragmentRange>> {
+        let _ = (range, data_len);
+        None
+    }
+
+    /// Returns the metadata of the given fragment without decoding.
+    ///
+    /// If the fragment is too broken to be parsed, this will return an `ErrorKind::CorruptedFragments` error.
+    ///
+    /// The default implementation returns an `ErrorKind::Other` error since the layout of fragments depends on
+    /// the implementations.
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        let _ = fragment;
+        track_panic!(
+            ErrorKind::Other,
+            "Fragment introspection is not supported by this implementation"
+        )
+    }
+
+    /// Verifies the given fragments without returning the decoded data.
+    ///
+    /// This checks the header (and the checksum if any) of each fragment and, if there are
+    /// more than `N` intact fragments (where `N = self.data_fragments()`),
+    /// checks the consistency of the fragments with their parities.
+    /// The result report classifies the indices of the fragments into missing, corrupt, consistent and unverified ones.
+    ///
+    /// The default implementation decodes the data from the given fragments, re-encodes it and
+    /// compares the result with the given fragments.
+    /// Thus, it cannot tell which fragment is corrupted; such fragments are reported as unidentified ones.
+    fn verify(&mut self, fragments: &[&Fragment]) -> VerifyReport {
+        verify::verify_by_reencoding(self, fragments)
+    }
+
     /// Reconstructs the fragment specified by the given index from other fragments.
     fn reconstruct(&mut self, index: usize, fragments: &[&Fragment]) -> Result<Vec<u8>> {
         track_assert!(
@@ -152,6 +378,292 @@ pub trait ErasureCode {
         let mut encoded = self.encode(&decoded)?;
         Ok(encoded.swap_remove(index))
     }
+
+    /// Reconstructs the fragments specified by the given indices from other fragments.
+    ///
+    /// The result vector contains the reconstructed fragments in the same order as `indices`.
+    ///
+    /// The default implementation decodes the original data once and re-encodes it.
+    fn reconstruct_many(
+        &mut self,
+        indices: &[usize],
+        fragments: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        for &index in indices {
+            track_assert!(
+                index < self.fragments().get(),
+                ErrorKind::Other,
+                "Too large index: index={}, fragments={}",
+                index,
+                self.fragments()
+            );
+        }
+        let decoded = self.decode(fragments)?;
+        let encoded = self.encode(&decoded)?;
+        Ok(indices.iter().map(|&i| encoded[i].clone()).collect())
+    }
+
+    /// Decodes the original data from the given fragments and their indices.
+    ///
+    /// Implementations may trust the given indices instead of examining the fragments to identify those.
+    ///
+    /// The default implementation ignores the indices and simply calls [`decode`].
+    ///
+    /// [`decode`]: #tymethod.decode
+    fn decode_indexed(&mut self, fragments: &[(usize, &Fragment)]) -> Result<Vec<u8>> {
+        let fragments = fragments.iter().map(|&(_, f)| f).collect::<Vec<_>>();
+        self.decode(&fragments)
+    }
+
+    /// Reconstructs the fragment specified by the given index from other fragments and their indices.
+    ///
+    /// Implementations may trust the given indices instead of examining the fragments to identify those.
+    ///
+    /// The default implementation ignores the indices and simply calls [`reconstruct`].
+    ///
+    /// [`reconstruct`]: #method.reconstruct
+    fn reconstruct_indexed(
+        &mut self,
+        index: usize,
+        fragments: &[(usize, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        let fragments = fragments.iter().map(|&(_, f)| f).collect::<Vec<_>>();
+        self.reconstruct(index, &fragments)
+    }
+
+    /// Returns `true` if the original data can be decoded from the fragments of which indices are `available`.
+    ///
+    /// The default implementation returns `true` if [`decode_plan`] succeeds.
+    ///
+    /// [`decode_plan`]: #method.decode_plan
+    fn is_decodable(&self, available: &[usize]) -> bool {
+        self.decode_plan(available, None).is_ok()
+    }
+
+    /// Returns the indices of the fragments to be read for decoding the original data.
+    ///
+    /// `available` is the indices of the fragments that can be read and
+    /// `costs[i]` (if specified) is the cost of reading the fragment `available[i]`.
+    /// The result is a cheapest subset of `available` (sorted by indices).
+    ///
+    /// If the data cannot be decoded from the available fragments,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// The default implementation selects `N` cheapest fragments (where `N = self.data_fragments()`)
+    /// preferring data fragments among the ones having the same cost.
+    fn decode_plan(&self, available: &[usize], costs: Option<&[u64]>) -> Result<Vec<usize>> {
+        let order = track!(plan::read_order(
+            available,
+            costs,
+            self.fragments().get(),
+            self.data_fragments().get()
+        ))?;
+        track!(plan::take_needed(&order, self.data_fragments().get()))
+    }
+
+    /// Returns the indices of the fragments to be read for reconstructing the fragment specified by `index`.
+    ///
+    /// The meanings of `available` and `costs` are the same as [`decode_plan`].
+    /// Note that the fragments are supposed to be read as a whole;
+    /// see [`repair_plan`] for the implementations that can reconstruct a fragment from parts of other fragments.
+    ///
+    /// The default implementation selects `N` cheapest fragments other than `index`.
+    ///
+    /// [`decode_plan`]: #method.decode_plan
+    /// [`repair_plan`]: #method.repair_plan
+    fn reconstruct_plan(
+        &self,
+        index: usize,
+        available: &[usize],
+        costs: Option<&[u64]>,
+    ) -> Result<Vec<usize>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        let mut order = track!(plan::read_order(
+            available,
+            costs,
+            self.fragments().get(),
+            self.data_fragments().get()
+        ))?;
+        order.retain(|&(i, _)| i != index);
+        track!(plan::take_needed(&order, self.data_fragments().get()))
+    }
+
+    /// Returns the byte ranges of the fragments that are required to reconstruct
+    /// the fragment specified by the given index.
+    ///
+    /// `available` is the indices of the fragments that can be read and
+    /// `fragment_len` is the size of each fragment.
+    /// The read ranges can be passed to [`reconstruct_partial`].
+    ///
+    /// The default implementation requires the whole of `N` available fragments
+    /// (where `N = self.data_fragments()`).
+    ///
+    /// [`reconstruct_partial`]: #method.reconstruct_partial
+    fn repair_plan(
+        &self,
+        index: usize,
+        available: &[usize],
+        fragment_len: usize,
+    ) -> Result<Vec<FragmentRange>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        track!(plan::full_repair_plan(
+            index,
+            available,
+            self.data_fragments().get(),
+            fragment_len
+        ))
+    }
+
+    /// Reconstructs the fragment specified by the given index from
+    /// the byte ranges of other fragments that are read according to [`repair_plan`].
+    ///
+    /// The default implementation only uses the parts that cover whole fragments.
+    ///
+    /// [`repair_plan`]: #method.repair_plan
+    fn reconstruct_partial(
+        &mut self,
+        index: usize,
+        parts: &[(FragmentRange, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        let fragments = parts
+            .iter()
+            .filter(|(r, f)| r.range.start == 0 && r.range.len() == f.len())
+            .map(|(_, f)| *f)
+            .collect::<Vec<_>>();
+        self.reconstruct(index, &fragments)
+    }
+}
+
+impl<T: ErasureCode + ?Sized> ErasureCode for Box<T> {
+    fn data_fragments(&self) -> NonZeroUsize {
+        (**self).data_fragments()
+    }
+    fn parity_fragments(&self) -> NonZeroUsize {
+        (**self).parity_fragments()
+    }
+    fn fragments(&self) -> NonZeroUsize {
+        (**self).fragments()
+    }
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<FragmentBuf>> {
+        (**self).encode(data)
+    }
+    fn fragment_size(&self, data_len: usize) -> Option<usize> {
+        (**self).fragment_size(data_len)
+    }
+    fn encode_into(&mut self, data: &[u8], buffers: &mut [&mut [u8]]) -> Result<()> {
+        (**self).encode_into(data, buffers)
+    }
+    fn update_parity(
+        &mut self,
+        data_index: usize,
+        old_data: &Fragment,
+        new_data: &Fragment,
+        parities: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        (**self).update_parity(data_index, old_data, new_data, parities)
+    }
+    fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        (**self).decode(fragments)
+    }
+    fn decode_range(&mut self, range: Range<usize>, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        (**self).decode_range(range, fragments)
+    }
+    fn fragment_ranges(&self, range: Range<usize>, data_len: usize) -> Option<Vec<FragmentRange>> {
+        (**self).fragment_ranges(range, data_len)
+    }
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        (**self).fragment_info(fragment)
+    }
+    fn verify(&mut self, fragments: &[&Fragment]) -> VerifyReport {
+        (**self).verify(fragments)
+    }
+    fn reconstruct(&mut self, index: usize, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        (**self).reconstruct(index, fragments)
+    }
+    fn reconstruct_many(
+        &mut self,
+        indices: &[usize],
+        fragments: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        (**self).reconstruct_many(indices, fragments)
+    }
+    fn decode_indexed(&mut self, fragments: &[(usize, &Fragment)]) -> Result<Vec<u8>> {
+        (**self).decode_indexed(fragments)
+    }
+    fn reconstruct_indexed(
+        &mut self,
+        index: usize,
+        fragments: &[(usize, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        (**self).reconstruct_indexed(index, fragments)
+    }
+    fn is_decodable(&self, available: &[usize]) -> bool {
+        (**self).is_decodable(available)
+    }
+    fn decode_plan(&self, available: &[usize], costs: Option<&[u64]>) -> Result<Vec<usize>> {
+        (**self).decode_plan(available, costs)
+    }
+    fn reconstruct_plan(
+        &self,
+        index: usize,
+        available: &[usize],
+        costs: Option<&[u64]>,
+    ) -> Result<Vec<usize>> {
+        (**self).reconstruct_plan(index, available, costs)
+    }
+    fn repair_plan(
+        &self,
+        index: usize,
+        available: &[usize],
+        fragment_len: usize,
+    ) -> Result<Vec<FragmentRange>> {
+        (**self).repair_plan(index, available, fragment_len)
+    }
+    fn reconstruct_partial(
+        &mut self,
+        index: usize,
+        parts: &[(FragmentRange, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        (**self).reconstruct_partial(index, parts)
+    }
+}
+
+/// Checks that the number of `buffers` is `fragments` and
+/// the size of each buffer is `fragment_size` (if specified).
+fn check_buffers(
+    buffers: &[&mut [u8]],
+    fragments: usize,
+    fragment_size: Option<usize>,
+) -> Result<()> {
+    track_assert_eq!(
+        buffers.len(),
+        fragments,
+        ErrorKind::InvalidInput,
+        "Unexpected number of buffers"
+    );
+    if let Some(size) = fragment_size {
+        for buffer in buffers {
+            track_assert_eq!(
+                buffer.len(),
+                size,
+                ErrorKind::InvalidInput,
+                "Unexpected buffer size"
+            );
+        }
+    }
+    Ok(())
 }
 
 /// This trait allows for building instances of an implementaion of [`ErasureCode`] trait.
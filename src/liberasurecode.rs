@@ -3,10 +3,20 @@
 //! [`ErasureCode`]: ../trait.ErasureCode.html
 //! [openstack/liberasurecode]: https://github.com/openstack/liberasurecode
 use crate::libec;
+use std::mem;
 use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::os::raw::c_long;
 use trackable::error::ErrorKindExt;
 
-use crate::{BuildCoder, ErasureCode, Error, ErrorKind, Fragment, FragmentBuf, Result};
+use crate::crc32;
+use crate::plan;
+use crate::pure_liberasurecode::{self, FragmentHeader};
+use crate::verify::{self, FragmentMeta};
+use crate::{
+    BuildCoder, ErasureCode, Error, ErrorKind, Fragment, FragmentBuf, FragmentInfo, Result,
+    VerifyReport,
+};
 
 pub use crate::libec::{Backend, Checksum};
 
@@ -50,14 +60,17 @@ impl LibErasureCoderBuilder {
 impl BuildCoder for LibErasureCoderBuilder {
     type Coder = LibErasureCoder;
     fn build_coder(&self) -> Result<Self::Coder> {
-        track!(
+        let inner = track!(
             libec::Builder::new(self.data_fragments, self.parity_fragments)
                 .backend(self.backend)
                 .checksum(self.checksum)
                 .finish()
-                .map(LibErasureCoder::from)
                 .map_err(Error::from)
-        )
+        )?;
+        Ok(LibErasureCoder {
+            inner,
+            settings: Some(self.clone()),
+        })
     }
     fn coder_id(&self) -> String {
         format!(
@@ -66,6 +79,106 @@ impl BuildCoder for LibErasureCoderBuilder {
         )
     }
 }
+#[cfg(feature = "serde")]
+impl serde::Serialize for LibErasureCoderBuilder {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let repr = crate::serialize::LibErasureCodeRepr {
+            version: crate::serialize::VERSION,
+            data_fragments: self.data_fragments,
+            parity_fragments: self.parity_fragments,
+            backend: backend_name(self.backend).to_owned(),
+            checksum: checksum_name(self.checksum).to_owned(),
+        };
+        serde::Serialize::serialize(&repr, serializer)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LibErasureCoderBuilder {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use crate::serialize::{self, invalid, LibErasureCodeRepr};
+
+        let repr: LibErasureCodeRepr = serialize::deserialize(deserializer)?;
+        let backend = parse_backend(&repr.backend).map_err(invalid)?;
+        let checksum = parse_checksum(&repr.checksum).map_err(invalid)?;
+        Ok(
+            LibErasureCoderBuilder::new(repr.data_fragments, repr.parity_fragments)
+                .backend(backend)
+                .checksum(checksum),
+        )
+    }
+}
+
+/// Returns the identifier of the given backend stored in fragment headers.
+fn backend_id(backend: Backend) -> u8 {
+    match backend {
+        Backend::JerasureRsVand => 1,
+        Backend::JerasureRsCauchy => 2,
+    }
+}
+
+/// Returns the number of bytes to which the data of each fragment is aligned by the given backend.
+///
+/// The word size of the backends is 32 bits (see `libec::Builder::finish`).
+fn alignment(backend: Backend) -> usize {
+    match backend {
+        Backend::JerasureRsVand => 4,
+        // A fragment of the Cauchy backend consists of `w` packets of `sizeof(long) * 128` bytes
+        Backend::JerasureRsCauchy => 32 * mem::size_of::<c_long>() * 128,
+    }
+}
+
+/// Returns the identifier of the given checksum algorithm stored in fragment headers.
+fn checksum_id(checksum: Checksum) -> u8 {
+    match checksum {
+        Checksum::None => 1,
+        Checksum::Crc32 => 2,
+        Checksum::Md5 => 3,
+    }
+}
+
+/// Returns the name of the given backend that is parsed by `parse_backend`.
+#[cfg(feature = "serde")]
+fn backend_name(backend: Backend) -> &'static str {
+    match backend {
+        Backend::JerasureRsVand => "JerasureRsVand",
+        Backend::JerasureRsCauchy => "JerasureRsCauchy",
+    }
+}
+
+/// Returns the name of the given checksum algorithm that is parsed by `parse_checksum`.
+#[cfg(feature = "serde")]
+fn checksum_name(checksum: Checksum) -> &'static str {
+    match checksum {
+        Checksum::None => "None",
+        Checksum::Crc32 => "Crc32",
+        Checksum::Md5 => "Md5",
+    }
+}
+
+/// Parses the name of a variant of `Backend` (e.g., `"JerasureRsVand"`).
+pub(crate) fn parse_backend(s: &str) -> Result<Backend> {
+    match s {
+        "JerasureRsVand" => Ok(Backend::JerasureRsVand),
+        "JerasureRsCauchy" => Ok(Backend::JerasureRsCauchy),
+        _ => track_panic!(ErrorKind::InvalidInput, "Unknown backend: {:?}", s),
+    }
+}
+
+/// Parses the name of a variant of `Checksum` (e.g., `"Crc32"`).
+pub(crate) fn parse_checksum(s: &str) -> Result<Checksum> {
+    match s {
+        "None" => Ok(Checksum::None),
+        "Crc32" => Ok(Checksum::Crc32),
+        "Md5" => Ok(Checksum::Md5),
+        _ => track_panic!(ErrorKind::InvalidInput, "Unknown checksum: {:?}", s),
+    }
+}
 
 /// An [`ErasureCode`] implementation based on [openstack/liberasurecode].
 ///
@@ -99,6 +212,9 @@ impl BuildCoder for LibErasureCoderBuilder {
 /// ```
 pub struct LibErasureCoder {
     inner: libec::ErasureCoder,
+
+    // `None` if the instance is made from a `libec::ErasureCoder`.
+    settings: Option<LibErasureCoderBuilder>,
 }
 impl LibErasureCoder {
     /// Makes a new `LibErasureCoder` instance with the default settings.
@@ -123,6 +239,39 @@ impl LibErasureCoder {
     pub fn into_inner(self) -> libec::ErasureCoder {
         self.inner
     }
+
+    fn coder_id(&self) -> String {
+        self.settings.as_ref().map_or_else(
+            || {
+                format!(
+                    "liberasurecode:{}:{}",
+                    self.data_fragments(),
+                    self.parity_fragments()
+                )
+            },
+            |s| s.coder_id(),
+        )
+    }
+
+    /// Checks that the given fragments were produced by a coder having the same settings as this instance.
+    ///
+    /// The fragments having broken headers are left to liberasurecode.
+    fn check_headers(&self, fragments: &[&Fragment]) -> Result<()> {
+        let backend_id = self.settings.as_ref().map(|s| backend_id(s.backend));
+        let checksum_id = self.settings.as_ref().map(|s| checksum_id(s.checksum));
+        for fragment in fragments {
+            if let Ok(header) = FragmentHeader::parse(fragment) {
+                track!(pure_liberasurecode::check_header(
+                    &header,
+                    &self.coder_id(),
+                    self.fragments().get(),
+                    backend_id,
+                    checksum_id
+                ))?;
+            }
+        }
+        Ok(())
+    }
 }
 impl ErasureCode for LibErasureCoder {
     fn data_fragments(&self) -> NonZeroUsize {
@@ -138,19 +287,119 @@ impl ErasureCode for LibErasureCoder {
         Ok(fragments)
     }
 
+    fn fragment_size(&self, data_len: usize) -> Option<usize> {
+        let backend = self.settings.as_ref()?.backend;
+        let payload_size = pure_liberasurecode::payload_size(
+            data_len,
+            self.data_fragments().get(),
+            alignment(backend),
+        );
+        Some(FragmentHeader::SIZE + payload_size)
+    }
+
+    fn encode_into(&mut self, data: &[u8], buffers: &mut [&mut [u8]]) -> Result<()> {
+        let fragment_size = self.fragment_size(data.len());
+        track!(crate::check_buffers(
+            buffers,
+            self.fragments().get(),
+            fragment_size
+        ))?;
+        let fragments = self.inner.encode(data)?;
+        for (buffer, fragment) in buffers.iter_mut().zip(fragments.iter()) {
+            track_assert_eq!(
+                buffer.len(),
+                fragment.len(),
+                ErrorKind::InvalidInput,
+                "Unexpected buffer size"
+            );
+        }
+        for (buffer, fragment) in buffers.iter_mut().zip(fragments.iter()) {
+            buffer.copy_from_slice(fragment);
+        }
+        Ok(())
+    }
+
     fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        track!(self.check_headers(fragments))?;
         let data = self.inner.decode(fragments)?;
         Ok(data)
     }
 
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        track!(pure_liberasurecode::fragment_info(fragment))
+    }
+
+    fn verify(&mut self, fragments: &[&Fragment]) -> VerifyReport {
+        let backend_id = self.settings.as_ref().map(|s| backend_id(s.backend));
+        verify::verify(self, fragments, FragmentHeader::SIZE, |fragment| {
+            let header = FragmentHeader::parse(fragment).ok()?;
+            let payload = &fragment[FragmentHeader::SIZE..];
+            let checksum_ok = match header.checksum_algorithm() {
+                Some(pure_liberasurecode::Checksum::Crc32) => {
+                    header.checksum[0] == crc32::crc32_liberasurecode(payload)
+                        || header.checksum[0] == crc32::crc32(payload)
+                }
+                _ => true,
+            };
+            let backend_ok = backend_id.is_none() || backend_id == Some(header.backend_id);
+            Some(FragmentMeta {
+                index: header.index,
+                data_len: header.data_len,
+                intact: backend_ok && !header.checksum_mismatch && checksum_ok,
+            })
+        })
+    }
+
+    fn decode_range(&mut self, range: Range<usize>, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        // The backends provided by liberasurecode are systematic,
+        // so the range can be extracted from the data fragments if all of those are available.
+        track!(self.check_headers(fragments))?;
+        let mut data_len = None;
+        let mut payloads = vec![None; self.data_fragments().get()];
+        for fragment in fragments {
+            let header = track!(FragmentHeader::parse(fragment))?;
+            data_len = Some(header.data_len);
+            if header.index < payloads.len() {
+                payloads[header.index] =
+                    Some((header.payload_size, &fragment[FragmentHeader::SIZE..]));
+            }
+        }
+        let data_len = track_assert_some!(data_len, ErrorKind::InvalidInput, "No fragments");
+        track!(plan::check_data_range(&range, data_len))?;
+
+        let payload_size = payloads.iter().flatten().map(|p| p.0).next().unwrap_or(0);
+        let ranges = plan::split_range(range.clone(), payload_size);
+        if ranges.iter().any(|(i, _)| payloads[*i].is_none()) {
+            let data = self.inner.decode(fragments)?;
+            track!(plan::check_data_range(&range, data.len()))?;
+            return Ok(data[range].to_vec());
+        }
+        let mut data = Vec::with_capacity(range.len());
+        for (i, r) in ranges {
+            let (size, payload) = payloads[i].expect("Never fails");
+            track_assert!(
+                size == payload_size && r.end <= payload.len(),
+                ErrorKind::CorruptedFragments,
+                "Unexpected payload size: index={}",
+                i
+            );
+            data.extend_from_slice(&payload[r]);
+        }
+        Ok(data)
+    }
+
     fn reconstruct(&mut self, index: usize, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        track!(self.check_headers(fragments))?;
         let fragment = self.inner.reconstruct(index, fragments.iter())?;
         Ok(fragment)
     }
 }
 impl From<libec::ErasureCoder> for LibErasureCoder {
     fn from(f: libec::ErasureCoder) -> Self {
-        LibErasureCoder { inner: f }
+        LibErasureCoder {
+            inner: f,
+            settings: None,
+        }
     }
 }
 
@@ -168,8 +417,11 @@ impl From<libec::Error> for Error {
 #[cfg(test)]
 mod tests {
     use std::num::NonZeroUsize;
+    use std::result::Result;
+    use trackable::error::{Failed, MainError};
 
     use super::*;
+    use crate::test_util::{non_zero, test_data};
     use crate::{ErasureCode, ErrorKind};
 
     #[test]
@@ -189,4 +441,50 @@ mod tests {
             coder.decode(&encoded[3..]).map_err(|e| *e.kind())
         );
     }
+
+    #[test]
+    fn encode_into_works() -> Result<(), MainError> {
+        for &backend in &[Backend::JerasureRsVand, Backend::JerasureRsCauchy] {
+            let mut coder = track!(LibErasureCoderBuilder::new(non_zero(4)?, non_zero(2)?)
+                .backend(backend)
+                .build_coder())?;
+            for &len in &[1, 103, 140_000] {
+                let data = test_data(len);
+                let encoded = track!(coder.encode(&data))?;
+                let size = track_assert_some!(coder.fragment_size(len), Failed);
+                assert!(encoded.iter().all(|f| f.len() == size));
+
+                let mut buffers = vec![vec![0; size]; 6];
+                let mut refs = buffers.iter_mut().map(|b| &mut b[..]).collect::<Vec<_>>();
+                track!(coder.encode_into(&data, &mut refs))?;
+                assert_eq!(buffers, encoded);
+
+                let mut refs = buffers.iter_mut().map(|b| &mut b[1..]).collect::<Vec<_>>();
+                assert_eq!(
+                    coder.encode_into(&data, &mut refs).map_err(|e| *e.kind()),
+                    Err(ErrorKind::InvalidInput)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn verify_detects_corrupt_data_fragment() -> Result<(), MainError> {
+        let mut coder = track!(LibErasureCoderBuilder::new(non_zero(4)?, non_zero(2)?)
+            .backend(Backend::JerasureRsVand)
+            .checksum(Checksum::Crc32)
+            .build_coder())?;
+        let mut encoded = track!(coder.encode(&test_data(1000)))?;
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        assert!(coder.verify(&fragments).is_healthy());
+
+        encoded[1][FragmentHeader::SIZE] ^= 1;
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        let report = coder.verify(&fragments);
+        assert_eq!(report.corrupt, vec![1]);
+        assert_eq!(report.consistent, vec![0, 2, 3, 4, 5]);
+        assert!(report.unidentified.is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,187 @@
+use futures::task::{self, Task};
+use futures::Async;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{ErrorKind, Result};
+
+/// Limits the number and the total size of the operations queued in a pool.
+///
+/// An operation is counted from its submission until the end of its execution.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    max_operations: Option<NonZeroUsize>,
+    max_bytes: Option<NonZeroUsize>,
+    state: Mutex<State>,
+}
+impl Limiter {
+    pub fn new(max_operations: Option<NonZeroUsize>, max_bytes: Option<NonZeroUsize>) -> Self {
+        Limiter {
+            max_operations,
+            max_bytes,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Counts an operation that handles `bytes` bytes.
+    ///
+    /// If the limits are exceeded, this will return an `ErrorKind::Overloaded` error.
+    /// Note that an operation larger than the byte limit is accepted if no other operations are queued.
+    pub fn acquire(self: &Arc<Self>, bytes: usize) -> Result<Permit> {
+        let mut state = self.lock();
+        if let Some(max) = self.max_operations {
+            track_assert!(
+                state.operations < max.get(),
+                ErrorKind::Overloaded,
+                "Too many queued operations: max={}",
+                max
+            );
+        }
+        if let Some(max) = self.max_bytes {
+            track_assert!(
+                state.operations == 0 || state.bytes + bytes <= max.get(),
+                ErrorKind::Overloaded,
+                "Too many queued bytes: max={}, queued={}, requested={}",
+                max,
+                state.bytes,
+                bytes
+            );
+        }
+        state.operations += 1;
+        state.bytes += bytes;
+        Ok(Permit {
+            limiter: Arc::clone(self),
+            bytes,
+        })
+    }
+
+    /// Returns `Async::Ready(())` if another operation can be accepted.
+    ///
+    /// Otherwise, the current task will be notified when a queued operation finishes.
+    pub fn poll_ready(&self) -> Async<()> {
+        let mut state = self.lock();
+        let full = self
+            .max_operations
+            .is_some_and(|max| state.operations >= max.get())
+            || self.max_bytes.is_some_and(|max| state.bytes >= max.get());
+        if full {
+            // A task that polls repeatedly is registered only once
+            if !state.waiters.iter().any(|w| w.will_notify_current()) {
+                state.waiters.push(task::current());
+            }
+            Async::NotReady
+        } else {
+            Async::Ready(())
+        }
+    }
+
+    pub fn queued_operations(&self) -> usize {
+        self.lock().operations
+    }
+
+    pub fn queued_bytes(&self) -> usize {
+        self.lock().bytes
+    }
+
+    fn release(&self, bytes: usize) {
+        let waiters = {
+            let mut state = self.lock();
+            state.operations -= 1;
+            state.bytes -= bytes;
+            std::mem::take(&mut state.waiters)
+        };
+        for waiter in waiters {
+            waiter.notify();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    operations: usize,
+    bytes: usize,
+    waiters: Vec<Task>,
+}
+
+/// A queued operation.
+///
+/// The operation is uncounted when this is dropped.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    limiter: Arc<Limiter>,
+    bytes: usize,
+}
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.release(self.bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::{self, Notify};
+    use futures::future;
+    use std::result::Result;
+    use trackable::error::MainError;
+
+    use super::*;
+
+    #[test]
+    fn limiter_works() -> Result<(), MainError> {
+        let limiter = Arc::new(Limiter::new(NonZeroUsize::new(2), NonZeroUsize::new(10)));
+
+        // An operation larger than the byte limit is accepted if the queue is empty
+        let large = track!(limiter.acquire(20))?;
+        assert_eq!(
+            limiter.acquire(1).map_err(|e| *e.kind()).err(),
+            Some(ErrorKind::Overloaded)
+        );
+        drop(large);
+
+        let a = track!(limiter.acquire(5))?;
+        let b = track!(limiter.acquire(5))?;
+        assert_eq!(limiter.queued_operations(), 2);
+        assert_eq!(limiter.queued_bytes(), 10);
+        assert_eq!(
+            limiter.acquire(0).map_err(|e| *e.kind()).err(),
+            Some(ErrorKind::Overloaded)
+        );
+        drop(a);
+        assert_eq!(
+            limiter.acquire(6).map_err(|e| *e.kind()).err(),
+            Some(ErrorKind::Overloaded)
+        );
+        let _c = track!(limiter.acquire(5))?;
+        drop(b);
+        assert_eq!(limiter.queued_operations(), 1);
+        assert_eq!(limiter.queued_bytes(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn poll_ready_registers_task_once() -> Result<(), MainError> {
+        struct NoopNotify;
+        impl Notify for NoopNotify {
+            fn notify(&self, _id: usize) {}
+        }
+
+        let limiter = Arc::new(Limiter::new(NonZeroUsize::new(1), None));
+        let permit = track!(limiter.acquire(0))?;
+        let limiter0 = Arc::clone(&limiter);
+        let mut ready =
+            executor::spawn(future::poll_fn(move || Ok::<_, ()>(limiter0.poll_ready())));
+        let notify = Arc::new(NoopNotify);
+        for _ in 0..3 {
+            assert_eq!(ready.poll_future_notify(&notify, 0), Ok(Async::NotReady));
+        }
+        assert_eq!(limiter.lock().waiters.len(), 1);
+
+        drop(permit);
+        assert_eq!(ready.poll_future_notify(&notify, 0), Ok(Async::Ready(())));
+        Ok(())
+    }
+}
@@ -0,0 +1,660 @@
+//! An [`ErasureCode`] implementation of Local Reconstruction Codes (LRC).
+//!
+//! [`ErasureCode`]: ../trait.ErasureCode.html
+use std::num::NonZeroUsize;
+use std::ops::Range;
+
+use crate::gf256;
+use crate::header::{self, FragmentHeader, Payloads};
+use crate::plan::{self, FragmentRange};
+use crate::verify;
+use crate::{
+    BuildCoder, ErasureCode, ErrorKind, Fragment, FragmentBuf, FragmentInfo, Result, VerifyReport,
+};
+
+/// An [`ErasureCode`] implementation of Local Reconstruction Codes (LRC).
+///
+/// The `K` data fragments are split into `L` local groups and each group has a local parity fragment
+/// (the XOR of the data fragments in the group).
+/// In addition, `G` global parity fragments are computed from all of the data fragments
+/// by using Reed-Solomon coding.
+///
+/// The fragments are ordered as follows:
+/// - `0..K`: data fragments
+/// - `K..K+L`: local parity fragments (the `i`-th one belongs to the `i`-th group)
+/// - `K+L..K+L+G`: global parity fragments
+///
+/// A lost data or local parity fragment can be reconstructed from the other fragments of its local group,
+/// which are far fewer than `K` (see [`repair_group`]).
+///
+/// [`ErasureCode`]: ../trait.ErasureCode.html
+/// [`repair_group`]: ./struct.LrcCoder.html#method.repair_group
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::{ErasureCode, ErrorKind};
+/// use ecpool::lrc::LrcCoder;
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data_fragments = NonZeroUsize::new(4).ok_or("invalid input")?;
+/// let local_groups = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let mut coder = LrcCoder::new(data_fragments, local_groups, 1)?;
+///
+/// // Encodes
+/// let data = vec![0, 1, 2, 3];
+/// let encoded = coder.encode(&data)?;
+/// let encoded = encoded.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+///
+/// // Decodes
+/// assert_eq!(Some(&data), coder.decode(&encoded[0..]).as_ref().ok());
+/// assert_eq!(Some(&data), coder.decode(&encoded[1..]).as_ref().ok());
+/// assert_eq!(Err(ErrorKind::InvalidInput), coder.decode(&encoded[4..]).map_err(|e| *e.kind()));
+///
+/// // Reconstructs the fragment #0 from its local group (i.e., #1 and the local parity #4)
+/// assert_eq!(coder.repair_group(0), Some(vec![1, 4]));
+/// let reconstructed = coder.reconstruct(0, &[encoded[1], encoded[4]])?;
+/// assert_eq!(reconstructed, encoded[0]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LrcCoder {
+    data_fragments: NonZeroUsize,
+    local_groups: Vec<Range<usize>>,
+    global_parities: Vec<Vec<u8>>,
+}
+impl LrcCoder {
+    /// The maximum number of total fragments.
+    pub const MAX_FRAGMENTS: usize = 256;
+
+    /// Makes a new `LrcCoder` instance.
+    ///
+    /// The data fragments are split into `local_groups` groups as evenly as possible.
+    ///
+    /// If `local_groups` exceeds `data_fragments`, or the total number of fragments exceeds `MAX_FRAGMENTS`,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(
+        data_fragments: NonZeroUsize,
+        local_groups: NonZeroUsize,
+        global_parity_fragments: usize,
+    ) -> Result<Self> {
+        let k = data_fragments.get();
+        let l = local_groups.get();
+        track_assert!(
+            l <= k,
+            ErrorKind::InvalidInput,
+            "Too many local groups: data_fragments={}, local_groups={}",
+            k,
+            l
+        );
+        track_assert!(
+            k + l + global_parity_fragments <= Self::MAX_FRAGMENTS,
+            ErrorKind::InvalidInput,
+            "Too many fragments: data_fragments={}, local_groups={}, global_parity_fragments={}",
+            k,
+            l,
+            global_parity_fragments
+        );
+
+        let mut start = 0;
+        let local_groups = (0..l)
+            .map(|i| {
+                let size = k / l + if i < k % l { 1 } else { 0 };
+                start += size;
+                start - size..start
+            })
+            .collect();
+        Ok(LrcCoder {
+            data_fragments,
+            local_groups,
+            global_parities: gf256::cauchy_matrix(global_parity_fragments, k),
+        })
+    }
+
+    /// Returns the number of local groups.
+    pub fn local_groups(&self) -> usize {
+        self.local_groups.len()
+    }
+
+    /// Returns the number of global parity fragments.
+    pub fn global_parity_fragments(&self) -> usize {
+        self.global_parities.len()
+    }
+
+    /// Returns the indices of the fragments that are required to reconstruct
+    /// the fragment specified by `index` by using only its local group.
+    ///
+    /// `None` is returned if `index` does not belong to any local group (i.e., it is a global parity).
+    pub fn repair_group(&self, index: usize) -> Option<Vec<usize>> {
+        let group = self.group_of(index)?;
+        let local_parity = self.data_fragments.get() + group;
+        Some(
+            self.local_groups[group]
+                .clone()
+                .chain(Some(local_parity))
+                .filter(|&i| i != index)
+                .collect(),
+        )
+    }
+
+    fn group_of(&self, index: usize) -> Option<usize> {
+        let k = self.data_fragments.get();
+        if index < k {
+            self.local_groups.iter().position(|g| g.contains(&index))
+        } else if index < k + self.local_groups.len() {
+            Some(index - k)
+        } else {
+            None
+        }
+    }
+
+    fn generator_row(&self, index: usize) -> Vec<u8> {
+        let k = self.data_fragments.get();
+        let l = self.local_groups.len();
+        if index < k {
+            (0..k).map(|i| (i == index) as u8).collect()
+        } else if index < k + l {
+            let group = &self.local_groups[index - k];
+            (0..k).map(|i| group.contains(&i) as u8).collect()
+        } else {
+            self.global_parities[index - k - l].clone()
+        }
+    }
+
+    /// Tries to compute the payload of `index` by XOR-ing the other payloads of its local group.
+    fn repair_locally(&self, index: usize, payloads: &Payloads) -> Option<Vec<u8>> {
+        let helpers = self
+            .repair_group(index)?
+            .into_iter()
+            .map(|i| payloads.slots[i])
+            .collect::<Option<Vec<_>>>()?;
+        let mut payload = vec![0; payloads.payload_len];
+        for h in helpers {
+            gf256::mul_slice_xor(1, h, &mut payload);
+        }
+        Some(payload)
+    }
+
+    /// Recovers all of the data payloads from the given fragments.
+    fn recover_data(&self, payloads: &Payloads) -> Result<Vec<Vec<u8>>> {
+        let k = self.data_fragments.get();
+        let mut data = payloads.slots[..k]
+            .iter()
+            .map(|s| s.map(|p| p.to_vec()))
+            .collect::<Vec<_>>();
+        for (i, d) in data.iter_mut().enumerate().filter(|(_, d)| d.is_none()) {
+            *d = self.repair_locally(i, payloads);
+        }
+        if data.iter().any(|d| d.is_none()) {
+            let available = payloads
+                .slots
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.is_some())
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            let rows = available
+                .iter()
+                .map(|&i| self.generator_row(i))
+                .collect::<Vec<_>>();
+            let selected = track_assert_some!(
+                gf256::select_independent_rows(&rows, k),
+                ErrorKind::InvalidInput,
+                "Unrecoverable: available={:?}",
+                available
+            );
+            let matrix = selected
+                .iter()
+                .map(|&i| rows[i].clone())
+                .collect::<Vec<_>>();
+            let inverse = gf256::invert_matrix(&matrix).expect("Never fails");
+            for (i, d) in data.iter_mut().enumerate().filter(|(_, d)| d.is_none()) {
+                let mut payload = vec![0; payloads.payload_len];
+                for (&c, &j) in inverse[i].iter().zip(selected.iter()) {
+                    let input = payloads.slots[available[j]].expect("Never fails");
+                    gf256::mul_slice_xor(c, input, &mut payload);
+                }
+                *d = Some(payload);
+            }
+        }
+        Ok(data.into_iter().map(|d| d.expect("Never fails")).collect())
+    }
+
+    /// Checks that the given payloads were produced with the same number of data fragments as this coder.
+    fn check_payloads(&self, payloads: &Payloads) -> Result<()> {
+        let expected = payloads.data_len.div_ceil(self.data_fragments.get());
+        track!(payloads.check_payload_len(&self.coder_id(), expected))
+    }
+
+    fn decode_payloads(&self, payloads: &Payloads) -> Result<Vec<u8>> {
+        track!(self.check_payloads(payloads))?;
+        let mut data = track!(self.recover_data(payloads))?.concat();
+        track_assert!(
+            payloads.data_len <= data.len(),
+            ErrorKind::CorruptedFragments,
+            "Too large data size: data_len={}, decoded={}",
+            payloads.data_len,
+            data.len()
+        );
+        data.truncate(payloads.data_len);
+        Ok(data)
+    }
+
+    fn reconstruct_payloads(&self, index: usize, payloads: &Payloads) -> Result<Vec<u8>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        if let Some(payload) = self.repair_locally(index, payloads) {
+            let mut fragment = vec![0; FragmentHeader::SIZE];
+            FragmentHeader::new(index, payloads.data_len).write_to(&mut fragment);
+            fragment.extend_from_slice(&payload);
+            return Ok(fragment);
+        }
+        track!(self.check_payloads(payloads))?;
+        let data = track!(self.recover_data(payloads))?;
+        let data = data.iter().map(|d| &d[..]).collect::<Vec<_>>();
+        Ok(self.make_fragment(index, payloads.data_len, &data))
+    }
+
+    fn make_fragment(&self, index: usize, data_len: usize, data: &[&[u8]]) -> FragmentBuf {
+        let payload_len = data.first().map_or(0, |d| d.len());
+        let mut fragment = vec![0; FragmentHeader::SIZE + payload_len];
+        FragmentHeader::new(index, data_len).write_to(&mut fragment);
+        let payload = &mut fragment[FragmentHeader::SIZE..];
+        for (&c, d) in self.generator_row(index).iter().zip(data.iter()) {
+            gf256::mul_slice_xor(c, d, payload);
+        }
+        fragment
+    }
+}
+impl ErasureCode for LrcCoder {
+    fn data_fragments(&self) -> NonZeroUsize {
+        self.data_fragments
+    }
+
+    fn parity_fragments(&self) -> NonZeroUsize {
+        unsafe { NonZeroUsize::new_unchecked(self.local_groups.len() + self.global_parities.len()) }
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<FragmentBuf>> {
+        let fragment_size = FragmentHeader::SIZE + data.len().div_ceil(self.data_fragments.get());
+        let mut fragments = vec![vec![0; fragment_size]; self.fragments().get()];
+        let mut buffers = fragments.iter_mut().map(|f| &mut f[..]).collect::<Vec<_>>();
+        track!(self.encode_into(data, &mut buffers))?;
+        Ok(fragments)
+    }
+
+    fn fragment_size(&self, data_len: usize) -> Option<usize> {
+        Some(FragmentHeader::SIZE + data_len.div_ceil(self.data_fragments.get()))
+    }
+
+    fn encode_into(&mut self, data: &[u8], buffers: &mut [&mut [u8]]) -> Result<()> {
+        let k = self.data_fragments.get();
+        let payload_len = data.len().div_ceil(k);
+        track!(crate::check_buffers(
+            buffers,
+            self.fragments().get(),
+            Some(FragmentHeader::SIZE + payload_len)
+        ))?;
+        let (data_buffers, parity_buffers) = buffers.split_at_mut(k);
+        header::write_data_fragments(data, payload_len, data_buffers);
+        let payloads = data_buffers
+            .iter()
+            .map(|b| &b[FragmentHeader::SIZE..])
+            .collect::<Vec<_>>();
+        for (i, buffer) in parity_buffers.iter_mut().enumerate() {
+            FragmentHeader::new(k + i, data.len()).write_to(buffer);
+            let payload = &mut buffer[FragmentHeader::SIZE..];
+            payload.iter_mut().for_each(|b| *b = 0);
+            for (&c, d) in self.generator_row(k + i).iter().zip(payloads.iter()) {
+                gf256::mul_slice_xor(c, d, payload);
+            }
+        }
+        Ok(())
+    }
+
+    fn update_parity(
+        &mut self,
+        data_index: usize,
+        old_data: &Fragment,
+        new_data: &Fragment,
+        parities: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        let k = self.data_fragments.get();
+        track_assert_eq!(
+            parities.len(),
+            self.parity_fragments().get(),
+            ErrorKind::InvalidInput
+        );
+        let delta = track!(header::parity_delta(
+            data_index, old_data, new_data, parities, k
+        ))?;
+        Ok(parities
+            .iter()
+            .enumerate()
+            .map(|(i, parity)| {
+                let mut parity = parity.to_vec();
+                let c = self.generator_row(k + i)[data_index];
+                gf256::mul_slice_xor(c, &delta, &mut parity[FragmentHeader::SIZE..]);
+                parity
+            })
+            .collect())
+    }
+
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        track!(header::fragment_info(fragment, "lrc"))
+    }
+
+    fn verify(&mut self, fragments: &[&Fragment]) -> VerifyReport {
+        verify::verify(self, fragments, FragmentHeader::SIZE, header::fragment_meta)
+    }
+
+    fn decode_plan(&self, available: &[usize], costs: Option<&[u64]>) -> Result<Vec<usize>> {
+        let k = self.data_fragments.get();
+        let order = track!(plan::read_order(
+            available,
+            costs,
+            self.fragments().get(),
+            k
+        ))?;
+
+        // Since the fragments are sorted by their costs,
+        // greedily selected independent ones are the cheapest decodable set.
+        let rows = order
+            .iter()
+            .map(|&(i, _)| self.generator_row(i))
+            .collect::<Vec<_>>();
+        let selected = track_assert_some!(
+            gf256::select_independent_rows(&rows, k),
+            ErrorKind::InvalidInput,
+            "Unrecoverable: available={:?}",
+            available
+        );
+        let mut plan = selected.into_iter().map(|i| order[i].0).collect::<Vec<_>>();
+        plan.sort_unstable();
+        Ok(plan)
+    }
+
+    fn reconstruct_plan(
+        &self,
+        index: usize,
+        available: &[usize],
+        costs: Option<&[u64]>,
+    ) -> Result<Vec<usize>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        let order = track!(plan::read_order(
+            available,
+            costs,
+            self.fragments().get(),
+            self.data_fragments.get()
+        ))?;
+        let cost_of = |i: usize| order.iter().find(|o| o.0 == i).map(|o| o.1);
+        let local = self.repair_group(index).and_then(|group| {
+            let cost = group.iter().map(|&i| cost_of(i)).sum::<Option<u64>>()?;
+            Some((cost, group))
+        });
+
+        let others = order
+            .iter()
+            .filter(|o| o.0 != index)
+            .map(|o| o.0)
+            .collect::<Vec<_>>();
+        let others_costs = others
+            .iter()
+            .map(|&i| cost_of(i).expect("Never fails"))
+            .collect::<Vec<_>>();
+        let global = self
+            .decode_plan(&others, Some(&others_costs))
+            .ok()
+            .map(|plan| {
+                let cost = plan
+                    .iter()
+                    .map(|&i| cost_of(i).expect("Never fails"))
+                    .sum::<u64>();
+                (cost, plan)
+            });
+        match (local, global) {
+            (Some(local), Some(global)) if global.0 < local.0 => Ok(global.1),
+            (Some(local), _) => Ok(local.1),
+            (None, Some(global)) => Ok(global.1),
+            (None, None) => track_panic!(
+                ErrorKind::InvalidInput,
+                "Unrecoverable: index={}, available={:?}",
+                index,
+                available
+            ),
+        }
+    }
+
+    fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.decode_payloads(&payloads))
+    }
+
+    fn reconstruct_many(
+        &mut self,
+        indices: &[usize],
+        fragments: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        for &index in indices {
+            track_assert!(
+                index < self.fragments().get(),
+                ErrorKind::Other,
+                "Too large index: index={}, fragments={}",
+                index,
+                self.fragments()
+            );
+        }
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.check_payloads(&payloads))?;
+        let mut data = None;
+        let mut reconstructed = Vec::with_capacity(indices.len());
+        for &index in indices {
+            if let Some(payload) = self.repair_locally(index, &payloads) {
+                let mut fragment = vec![0; FragmentHeader::SIZE];
+                FragmentHeader::new(index, payloads.data_len).write_to(&mut fragment);
+                fragment.extend_from_slice(&payload);
+                reconstructed.push(fragment);
+                continue;
+            }
+            if data.is_none() {
+                data = Some(track!(self.recover_data(&payloads))?);
+            }
+            let data = data.iter().flatten().map(|d| &d[..]).collect::<Vec<_>>();
+            reconstructed.push(self.make_fragment(index, payloads.data_len, &data));
+        }
+        Ok(reconstructed)
+    }
+
+    fn decode_indexed(&mut self, fragments: &[(usize, &Fragment)]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_indexed_fragments(
+            fragments,
+            self.fragments().get()
+        ))?;
+        track!(self.decode_payloads(&payloads))
+    }
+
+    fn decode_range(&mut self, range: Range<usize>, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.check_payloads(&payloads))?;
+        track!(plan::check_data_range(&range, payloads.data_len))?;
+        let mut data = Vec::with_capacity(range.len());
+        for (i, r) in plan::split_range(range, payloads.payload_len) {
+            if let Some(payload) = payloads.slots[i] {
+                data.extend_from_slice(&payload[r]);
+            } else {
+                let mut recovered = track!(self.recover_data(&payloads.slice(r)))?;
+                data.append(&mut recovered[i]);
+            }
+        }
+        Ok(data)
+    }
+
+    fn fragment_ranges(&self, range: Range<usize>, data_len: usize) -> Option<Vec<FragmentRange>> {
+        let payload_len = data_len.div_ceil(self.data_fragments.get());
+        plan::data_fragment_ranges(range, data_len, payload_len, FragmentHeader::SIZE)
+    }
+
+    fn reconstruct(&mut self, index: usize, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.reconstruct_payloads(index, &payloads))
+    }
+
+    fn reconstruct_indexed(
+        &mut self,
+        index: usize,
+        fragments: &[(usize, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_indexed_fragments(
+            fragments,
+            self.fragments().get()
+        ))?;
+        track!(self.reconstruct_payloads(index, &payloads))
+    }
+}
+impl BuildCoder for LrcCoder {
+    type Coder = Self;
+
+    fn build_coder(&self) -> Result<Self::Coder> {
+        Ok(self.clone())
+    }
+
+    fn coder_id(&self) -> String {
+        format!(
+            "lrc:{}:{}:{}",
+            self.data_fragments,
+            self.local_groups.len(),
+            self.global_parities.len()
+        )
+    }
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for LrcCoder {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let repr = crate::serialize::LrcRepr {
+            version: crate::serialize::VERSION,
+            data_fragments: self.data_fragments,
+            local_groups: NonZeroUsize::new(self.local_groups.len()).expect("Never fails"),
+            global_parity_fragments: self.global_parities.len(),
+        };
+        serde::Serialize::serialize(&repr, serializer)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LrcCoder {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr: crate::serialize::LrcRepr = crate::serialize::deserialize(deserializer)?;
+        LrcCoder::new(
+            repr.data_fragments,
+            repr.local_groups,
+            repr.global_parity_fragments,
+        )
+        .map_err(crate::serialize::invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::MainError;
+
+    use super::*;
+    use crate::test_util::{non_zero, test_data};
+    use crate::{ErasureCode, ErrorKind};
+
+    #[test]
+    fn it_works() -> Result<(), MainError> {
+        let mut coder = track!(LrcCoder::new(non_zero(4)?, non_zero(2)?, 2))?;
+        let data = vec![0, 1, 2, 3];
+        let encoded = track!(coder.encode(&data))?;
+        let encoded = encoded.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+
+        assert_eq!(Some(&data), coder.decode(&encoded[0..]).as_ref().ok());
+        assert_eq!(Some(&data), coder.decode(&encoded[1..]).as_ref().ok());
+        assert_eq!(Some(&data), coder.decode(&encoded[2..]).as_ref().ok());
+        assert_eq!(Some(&data), coder.decode(&encoded[3..]).as_ref().ok());
+        assert_eq!(
+            Err(ErrorKind::InvalidInput),
+            coder.decode(&encoded[5..]).map_err(|e| *e.kind())
+        );
+
+        let new = track!(coder.encode(&[0, 1, 9, 3]))?;
+        assert_eq!(
+            track!(coder.update_parity(2, encoded[2], &new[2], &encoded[4..]))?,
+            new[4..].to_vec()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reconstruct_works() -> Result<(), MainError> {
+        let mut coder = track!(LrcCoder::new(non_zero(6)?, non_zero(2)?, 2))?;
+        let data = test_data(1000);
+        let encoded = track!(coder.encode(&data))?;
+        for index in 0..coder.fragments().get() {
+            // From the local group only
+            if let Some(group) = coder.repair_group(index) {
+                assert_eq!(group.len(), 3);
+                let fragments = group.iter().map(|&i| &encoded[i][..]).collect::<Vec<_>>();
+                assert_eq!(
+                    track!(coder.reconstruct(index, &fragments))?,
+                    encoded[index]
+                );
+            }
+
+            // From all of the other fragments except one in the same local group
+            let skip = coder.repair_group(index).map_or(index, |g| g[0]);
+            let fragments = (0..coder.fragments().get())
+                .filter(|&i| i != index && i != skip)
+                .map(|i| &encoded[i][..])
+                .collect::<Vec<_>>();
+            assert_eq!(
+                track!(coder.reconstruct(index, &fragments))?,
+                encoded[index]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn plans_work() -> Result<(), MainError> {
+        let coder = track!(LrcCoder::new(non_zero(6)?, non_zero(2)?, 2))?;
+        let all = (0..10).collect::<Vec<_>>();
+
+        // The local group of #1 is {0, 1, 2} and its local parity is #6
+        assert_eq!(track!(coder.reconstruct_plan(1, &all, None))?, [0, 2, 6]);
+        let costs = [1, 1, 100, 1, 1, 1, 1, 1, 1, 1];
+        assert_eq!(
+            track!(coder.reconstruct_plan(1, &all, Some(&costs)))?,
+            [0, 3, 4, 5, 6, 8]
+        );
+
+        // {0, 1, 2, 6} are dependent, so #7 is required in addition to the others
+        let available = [0, 1, 2, 6, 3, 4, 7];
+        assert!(coder.is_decodable(&available));
+        assert_eq!(
+            track!(coder.decode_plan(&available, Some(&[1, 1, 1, 1, 2, 2, 2])))?,
+            [0, 1, 2, 3, 4, 7]
+        );
+        assert!(!coder.is_decodable(&[0, 1, 2, 6, 3, 4]));
+        Ok(())
+    }
+}
@@ -0,0 +1,461 @@
+//! Metrics of [`ErasureCoderPool`].
+//!
+//! The metrics are collected for each pair of the identifier of a coder (see [`BuildCoder::coder_id`])
+//! and an operation (i.e., the name of the method of [`ErasureCoderPool`] such as `"encode"`).
+//! The metrics of the operations of a pool are registered when the pool is built,
+//! so those appear in the snapshots (as zeros) before the operations are executed.
+//! Those can be obtained as a [`MetricsSnapshot`], which can be exported
+//! in the [Prometheus text exposition format].
+//!
+//! [`ErasureCoderPool`]: ../struct.ErasureCoderPool.html
+//! [`BuildCoder::coder_id`]: ../trait.BuildCoder.html#tymethod.coder_id
+//! [`MetricsSnapshot`]: ./struct.MetricsSnapshot.html
+//! [Prometheus text exposition format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::ErrorKind;
+
+/// The upper bounds of the buckets of the histograms.
+pub const BUCKETS: [Duration; 13] = [
+    Duration::from_micros(10),
+    Duration::from_micros(50),
+    Duration::from_micros(100),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+/// A registry of the metrics of pools.
+///
+/// Each pool has its own registry by default.
+/// A registry can be shared by multiple pools via [`ErasureCoderPoolBuilder::metrics`].
+///
+/// [`ErasureCoderPoolBuilder::metrics`]: ../struct.ErasureCoderPoolBuilder.html#method.metrics
+///
+/// # Examples
+///
+/// ```
+/// # extern crate ecpool;
+/// # extern crate fibers_global;
+/// use ecpool::replica::ReplicaCoder;
+/// use ecpool::ErasureCoderPool;
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let parity_fragments = NonZeroUsize::new(1).ok_or("invalid input")?;
+/// let pool = ErasureCoderPool::new(ReplicaCoder::new(data_fragments, parity_fragments));
+/// fibers_global::execute(pool.encode(vec![0, 1, 2]))?;
+///
+/// let snapshot = pool.metrics().snapshot();
+/// let encode = snapshot.get("replica:2:1", "encode").ok_or("no metrics")?;
+/// assert_eq!(encode.completed, 1);
+/// assert_eq!(encode.bytes, 3);
+/// assert!(snapshot
+///     .to_prometheus()
+///     .contains(r#"ecpool_completed_operations_total{coder="replica:2:1",operation="encode"} 1"#));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    operations: Arc<Mutex<HashMap<OperationKey, Arc<OperationMetrics>>>>,
+}
+impl Metrics {
+    /// Makes a new empty `Metrics` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the snapshot of the current metrics.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut operations = lock(&self.operations)
+            .iter()
+            .map(|((coder_id, operation), metrics)| metrics.snapshot(coder_id, operation))
+            .collect::<Vec<_>>();
+        operations.sort_by(|a, b| (&a.coder_id, a.operation).cmp(&(&b.coder_id, b.operation)));
+        MetricsSnapshot { operations }
+    }
+
+    pub(crate) fn operation(
+        &self,
+        coder_id: String,
+        operation: &'static str,
+    ) -> Arc<OperationMetrics> {
+        let mut operations = lock(&self.operations);
+        Arc::clone(operations.entry((coder_id, operation)).or_default())
+    }
+}
+
+/// The pair of a coder identifier and an operation name.
+type OperationKey = (String, &'static str);
+
+/// The metrics of an operation of a coder.
+#[derive(Debug, Default)]
+pub(crate) struct OperationMetrics {
+    state: Mutex<OperationState>,
+}
+impl OperationMetrics {
+    /// Records that an operation has failed without being executed.
+    pub fn failed(&self, kind: ErrorKind) {
+        *lock(&self.state).failed.entry(kind).or_default() += 1;
+    }
+
+    pub fn queued(&self) {
+        lock(&self.state).queued += 1;
+    }
+
+    /// Records that an operation has been dequeued after waiting for `wait_time`.
+    pub fn dequeued(&self, wait_time: Duration) {
+        let mut state = lock(&self.state);
+        state.queued -= 1;
+        state.wait_time.observe(wait_time);
+    }
+
+    pub fn cancelled(&self) {
+        lock(&self.state).cancelled += 1;
+    }
+
+    pub fn started(&self) {
+        lock(&self.state).running += 1;
+    }
+
+    /// Records the result of an operation that has handled `bytes` bytes.
+    pub fn finished(&self, execution_time: Duration, bytes: usize, error: Option<ErrorKind>) {
+        let mut state = lock(&self.state);
+        state.running -= 1;
+        state.execution_time.observe(execution_time);
+        if let Some(kind) = error {
+            *state.failed.entry(kind).or_default() += 1;
+        } else {
+            state.completed += 1;
+            state.bytes += bytes as u64;
+        }
+    }
+
+    fn snapshot(&self, coder_id: &str, operation: &'static str) -> OperationSnapshot {
+        let state = lock(&self.state);
+        let mut failed = state
+            .failed
+            .iter()
+            .map(|(&kind, &count)| (kind, count))
+            .collect::<Vec<_>>();
+        failed.sort_by_key(|&(kind, _)| format!("{:?}", kind));
+        OperationSnapshot {
+            coder_id: coder_id.to_owned(),
+            operation,
+            queued: state.queued,
+            running: state.running,
+            completed: state.completed,
+            cancelled: state.cancelled,
+            failed,
+            bytes: state.bytes,
+            wait_time: state.wait_time.clone(),
+            execution_time: state.execution_time.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct OperationState {
+    queued: u64,
+    running: u64,
+    completed: u64,
+    cancelled: u64,
+    failed: HashMap<ErrorKind, u64>,
+    bytes: u64,
+    wait_time: HistogramSnapshot,
+    execution_time: HistogramSnapshot,
+}
+
+/// A snapshot of [`Metrics`].
+///
+/// [`Metrics`]: ./struct.Metrics.html
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    /// The metrics of the operations sorted by the coder identifiers and the operation names.
+    pub operations: Vec<OperationSnapshot>,
+}
+impl MetricsSnapshot {
+    /// Returns the metrics of the given operation of the given coder.
+    pub fn get(&self, coder_id: &str, operation: &str) -> Option<&OperationSnapshot> {
+        self.operations
+            .iter()
+            .find(|o| o.coder_id == coder_id && o.operation == operation)
+    }
+
+    /// Returns the metrics in the Prometheus text exposition format.
+    ///
+    /// Every metric has the `coder` and `operation` labels,
+    /// and `ecpool_failed_operations_total` also has the `kind` label (e.g., `"Overloaded"`).
+    pub fn to_prometheus(&self) -> String {
+        let mut buf = String::new();
+        self.write_prometheus(&mut buf).expect("Never fails");
+        buf
+    }
+
+    fn write_prometheus(&self, w: &mut String) -> fmt::Result {
+        self.write_metric(
+            w,
+            "ecpool_queued_operations",
+            "gauge",
+            "The number of the operations waiting to be executed.",
+            |o| o.queued,
+        )?;
+        self.write_metric(
+            w,
+            "ecpool_running_operations",
+            "gauge",
+            "The number of the operations being executed.",
+            |o| o.running,
+        )?;
+        self.write_metric(
+            w,
+            "ecpool_completed_operations_total",
+            "counter",
+            "The number of the operations completed successfully.",
+            |o| o.completed,
+        )?;
+        self.write_metric(
+            w,
+            "ecpool_cancelled_operations_total",
+            "counter",
+            "The number of the operations skipped because their futures were dropped.",
+            |o| o.cancelled,
+        )?;
+        self.write_metric(
+            w,
+            "ecpool_processed_bytes_total",
+            "counter",
+            "The total size of the data and fragments given to the completed operations.",
+            |o| o.bytes,
+        )?;
+
+        let name = "ecpool_failed_operations_total";
+        writeln!(
+            w,
+            "# HELP {} The number of the failed operations (including the rejected ones).",
+            name
+        )?;
+        writeln!(w, "# TYPE {} counter", name)?;
+        for o in &self.operations {
+            for (kind, count) in &o.failed {
+                writeln!(
+                    w,
+                    "{}{{{},kind=\"{:?}\"}} {}",
+                    name,
+                    o.labels(),
+                    kind,
+                    count
+                )?;
+            }
+        }
+
+        self.write_histogram(
+            w,
+            "ecpool_wait_duration_seconds",
+            "The time the operations spent in the queue.",
+            |o| &o.wait_time,
+        )?;
+        self.write_histogram(
+            w,
+            "ecpool_execution_duration_seconds",
+            "The time spent executing the operations.",
+            |o| &o.execution_time,
+        )
+    }
+
+    fn write_metric<F>(
+        &self,
+        w: &mut String,
+        name: &str,
+        kind: &str,
+        help: &str,
+        f: F,
+    ) -> fmt::Result
+    where
+        F: Fn(&OperationSnapshot) -> u64,
+    {
+        writeln!(w, "# HELP {} {}", name, help)?;
+        writeln!(w, "# TYPE {} {}", name, kind)?;
+        for o in &self.operations {
+            writeln!(w, "{}{{{}}} {}", name, o.labels(), f(o))?;
+        }
+        Ok(())
+    }
+
+    fn write_histogram<F>(&self, w: &mut String, name: &str, help: &str, f: F) -> fmt::Result
+    where
+        F: Fn(&OperationSnapshot) -> &HistogramSnapshot,
+    {
+        writeln!(w, "# HELP {} {}", name, help)?;
+        writeln!(w, "# TYPE {} histogram", name)?;
+        for o in &self.operations {
+            let labels = o.labels();
+            let h = f(o);
+            for (upper, count) in BUCKETS.iter().zip(h.buckets.iter()) {
+                let le = upper.as_secs_f64();
+                writeln!(w, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, le, count)?;
+            }
+            writeln!(w, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, h.count)?;
+            writeln!(w, "{}_sum{{{}}} {}", name, labels, h.sum.as_secs_f64())?;
+            writeln!(w, "{}_count{{{}}} {}", name, labels, h.count)?;
+        }
+        Ok(())
+    }
+}
+
+/// The metrics of an operation of a coder.
+#[derive(Debug, Clone)]
+pub struct OperationSnapshot {
+    /// The identifier of the coder.
+    pub coder_id: String,
+
+    /// The name of the operation (e.g., `"encode"`).
+    pub operation: &'static str,
+
+    /// The number of the operations waiting to be executed.
+    pub queued: u64,
+
+    /// The number of the operations being executed.
+    pub running: u64,
+
+    /// The number of the operations completed successfully.
+    pub completed: u64,
+
+    /// The number of the operations skipped because their futures were dropped.
+    pub cancelled: u64,
+
+    /// The number of the failed operations for each error kind.
+    ///
+    /// This includes the operations rejected by the pool (`ErrorKind::Overloaded`)
+    /// and the ones discarded due to their deadlines (`ErrorKind::Timeout`).
+    pub failed: Vec<(ErrorKind, u64)>,
+
+    /// The total size of the data and fragments given to the completed operations.
+    pub bytes: u64,
+
+    /// The histogram of the time the operations spent in the queue.
+    pub wait_time: HistogramSnapshot,
+
+    /// The histogram of the time spent executing the operations.
+    pub execution_time: HistogramSnapshot,
+}
+impl OperationSnapshot {
+    fn labels(&self) -> String {
+        format!(
+            "coder=\"{}\",operation=\"{}\"",
+            escape(&self.coder_id),
+            escape(self.operation)
+        )
+    }
+}
+
+/// A histogram of durations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    /// The cumulative counts of the observations less than or equal to each of `BUCKETS`.
+    pub buckets: [u64; BUCKETS.len()],
+
+    /// The number of the observations.
+    pub count: u64,
+
+    /// The sum of the observations.
+    pub sum: Duration,
+}
+impl HistogramSnapshot {
+    fn observe(&mut self, value: Duration) {
+        for (upper, count) in BUCKETS.iter().zip(self.buckets.iter_mut()) {
+            if value <= *upper {
+                *count += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::{Failed, MainError};
+
+    use super::*;
+
+    #[test]
+    fn metrics_works() -> Result<(), MainError> {
+        let metrics = Metrics::new();
+        let encode = metrics.operation("foo:\"1\"".to_owned(), "encode");
+        encode.failed(ErrorKind::Overloaded);
+        encode.queued();
+        encode.queued();
+        encode.dequeued(Duration::from_micros(30));
+        encode.started();
+        encode.finished(Duration::from_millis(2), 100, None);
+        encode.dequeued(Duration::from_secs(20));
+        encode.started();
+        encode.finished(Duration::from_millis(3), 50, Some(ErrorKind::InvalidInput));
+        metrics.operation("bar".to_owned(), "decode").cancelled();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.operations.len(), 2);
+        assert_eq!(snapshot.operations[0].coder_id, "bar");
+        let encode = track_assert_some!(snapshot.get("foo:\"1\"", "encode"), Failed);
+        assert_eq!(encode.queued, 0);
+        assert_eq!(encode.completed, 1);
+        assert_eq!(encode.bytes, 100);
+        assert_eq!(
+            encode.failed,
+            [(ErrorKind::InvalidInput, 1), (ErrorKind::Overloaded, 1)]
+        );
+        assert_eq!(encode.wait_time.count, 2);
+        assert_eq!(encode.wait_time.buckets[0], 0);
+        assert_eq!(encode.wait_time.buckets[1], 1);
+        assert_eq!(encode.wait_time.buckets[12], 1);
+        assert_eq!(encode.execution_time.sum, Duration::from_millis(5));
+
+        let text = snapshot.to_prometheus();
+        let labels = r#"coder="foo:\"1\"",operation="encode""#;
+        for line in &[
+            format!("ecpool_completed_operations_total{{{}}} 1", labels),
+            format!(
+                "ecpool_failed_operations_total{{{},kind=\"Overloaded\"}} 1",
+                labels
+            ),
+            format!(
+                "ecpool_wait_duration_seconds_bucket{{{},le=\"0.00005\"}} 1",
+                labels
+            ),
+            format!(
+                "ecpool_wait_duration_seconds_bucket{{{},le=\"+Inf\"}} 2",
+                labels
+            ),
+            format!("ecpool_execution_duration_seconds_sum{{{}}} 0.005", labels),
+            r#"ecpool_cancelled_operations_total{coder="bar",operation="decode"} 1"#.to_owned(),
+        ] {
+            assert!(text.lines().any(|l| l == line), "{}", line);
+        }
+        Ok(())
+    }
+}
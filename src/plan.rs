@@ -0,0 +1,215 @@
+use std::ops::Range;
+
+use crate::{ErrorKind, Result};
+
+/// A byte range of a fragment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FragmentRange {
+    /// The index of the fragment.
+    pub index: usize,
+
+    /// The byte range within the fragment.
+    pub range: Range<usize>,
+}
+impl FragmentRange {
+    /// Makes a new `FragmentRange` instance.
+    pub fn new(index: usize, range: Range<usize>) -> Self {
+        FragmentRange { index, range }
+    }
+}
+
+/// Returns a repair plan that reads the whole of the first `needed` available fragments except `index`.
+pub(crate) fn full_repair_plan(
+    index: usize,
+    available: &[usize],
+    needed: usize,
+    fragment_len: usize,
+) -> Result<Vec<FragmentRange>> {
+    let mut helpers = available.to_vec();
+    helpers.sort_unstable();
+    helpers.dedup();
+    let plan = helpers
+        .into_iter()
+        .filter(|&i| i != index)
+        .take(needed)
+        .map(|i| FragmentRange::new(i, 0..fragment_len))
+        .collect::<Vec<_>>();
+    track_assert_eq!(
+        plan.len(),
+        needed,
+        ErrorKind::InvalidInput,
+        "Too few available fragments: available={:?}",
+        available
+    );
+    Ok(plan)
+}
+
+/// Sorts the given available fragments in order of preference for reading.
+///
+/// Cheaper fragments come first and, among the ones having the same cost,
+/// data fragments (i.e., the ones of which indices are less than `data_fragments`) are preferred
+/// since those can be used without decoding computation.
+///
+/// `costs[i]` is the cost of reading `available[i]` (all costs are regarded as `1` if `costs` is `None`).
+/// If an index appears more than once, the cheapest one is used.
+pub(crate) fn read_order(
+    available: &[usize],
+    costs: Option<&[u64]>,
+    fragments: usize,
+    data_fragments: usize,
+) -> Result<Vec<(usize, u64)>> {
+    if let Some(costs) = costs {
+        track_assert_eq!(
+            costs.len(),
+            available.len(),
+            ErrorKind::InvalidInput,
+            "The number of costs differs from the number of available fragments"
+        );
+    }
+    let mut order = Vec::with_capacity(available.len());
+    for (i, &index) in available.iter().enumerate() {
+        track_assert!(
+            index < fragments,
+            ErrorKind::InvalidInput,
+            "Too large index: index={}, fragments={}",
+            index,
+            fragments
+        );
+        let cost = costs.map_or(1, |c| c[i]);
+        order.push((cost, index >= data_fragments, index));
+    }
+    order.sort_unstable();
+    let mut seen = vec![false; fragments];
+    Ok(order
+        .into_iter()
+        .filter(|&(_, _, i)| !std::mem::replace(&mut seen[i], true))
+        .map(|(cost, _, i)| (i, cost))
+        .collect())
+}
+
+/// Returns the sorted indices of the first `needed` fragments of `order`.
+pub(crate) fn take_needed(order: &[(usize, u64)], needed: usize) -> Result<Vec<usize>> {
+    let mut plan = order
+        .iter()
+        .take(needed)
+        .map(|&(i, _)| i)
+        .collect::<Vec<_>>();
+    track_assert_eq!(
+        plan.len(),
+        needed,
+        ErrorKind::InvalidInput,
+        "Too few available fragments: available={:?}",
+        order.iter().map(|&(i, _)| i).collect::<Vec<_>>()
+    );
+    plan.sort_unstable();
+    Ok(plan)
+}
+
+/// Returns the bytes of the given fragment range if it is contained in `parts`.
+pub(crate) fn find_range<T: AsRef<[u8]>>(
+    parts: &[(FragmentRange, T)],
+    index: usize,
+    range: Range<usize>,
+) -> Option<&[u8]> {
+    parts.iter().find_map(|(r, bytes)| {
+        let bytes = bytes.as_ref();
+        if r.index == index
+            && r.range.start <= range.start
+            && range.end <= r.range.end
+            && bytes.len() == r.range.len()
+        {
+            Some(&bytes[range.start - r.range.start..range.end - r.range.start])
+        } else {
+            None
+        }
+    })
+}
+
+/// Splits the given range of the original data into the payload ranges of the data fragments.
+///
+/// It is assumed that the `i`-th data fragment holds the `payload_len` bytes of the data
+/// starting from `i * payload_len`.
+pub(crate) fn split_range(range: Range<usize>, payload_len: usize) -> Vec<(usize, Range<usize>)> {
+    let mut ranges = Vec::new();
+    let mut start = range.start;
+    while start < range.end {
+        let index = start / payload_len;
+        let end = std::cmp::min(range.end, (index + 1) * payload_len);
+        ranges.push((
+            index,
+            start - index * payload_len..end - index * payload_len,
+        ));
+        start = end;
+    }
+    ranges
+}
+
+/// Returns the fragment ranges that hold the given range of the original data
+/// by using the layout described in `split_range`.
+///
+/// `None` is returned if `range` is out of `0..data_len`.
+pub(crate) fn data_fragment_ranges(
+    range: Range<usize>,
+    data_len: usize,
+    payload_len: usize,
+    header_size: usize,
+) -> Option<Vec<FragmentRange>> {
+    if range.start > range.end || range.end > data_len {
+        return None;
+    }
+    Some(
+        split_range(range, payload_len)
+            .into_iter()
+            .map(|(i, r)| FragmentRange::new(i, header_size + r.start..header_size + r.end))
+            .collect(),
+    )
+}
+
+/// Checks that `range` is a valid range of the data of which size is `data_len`.
+pub(crate) fn check_data_range(range: &Range<usize>, data_len: usize) -> Result<()> {
+    track_assert!(
+        range.start <= range.end && range.end <= data_len,
+        ErrorKind::InvalidInput,
+        "Out of range: range={:?}, data_len={}",
+        range,
+        data_len
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::MainError;
+
+    use crate::reedsolomon::ReedSolomonCoder;
+    use crate::test_util::non_zero;
+    use crate::{ErasureCode, ErrorKind};
+
+    #[test]
+    fn default_plans_work() -> Result<(), MainError> {
+        let coder = track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?;
+
+        assert!(coder.is_decodable(&[5, 3, 1, 0]));
+        assert!(!coder.is_decodable(&[5, 3, 3, 1]));
+        assert_eq!(
+            coder.decode_plan(&[5, 4, 3, 2, 1], None).ok(),
+            Some(vec![1, 2, 3, 4])
+        );
+        assert_eq!(
+            coder
+                .decode_plan(&[5, 4, 3, 2, 1], Some(&[1, 1, 1, 1, 9]))
+                .ok(),
+            Some(vec![2, 3, 4, 5])
+        );
+        assert_eq!(
+            coder.decode_plan(&[0, 1, 6], None).map_err(|e| *e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            coder.reconstruct_plan(1, &[0, 1, 2, 3, 4, 5], None).ok(),
+            Some(vec![0, 2, 3, 4])
+        );
+        Ok(())
+    }
+}
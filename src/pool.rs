@@ -1,10 +1,27 @@
-use fibers_tasque::{AsyncCall, DefaultCpuTaskQueue, TaskQueueExt};
+#[cfg(feature = "bytes")]
+use bytes::{Bytes, BytesMut};
+use fibers::sync::oneshot;
+use fibers::time::timer::{self, Timeout};
 use futures::{Async, Future, Poll};
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use trackable::error::ErrorKindExt;
 
-use crate::{BuildCoder, ErasureCode, Error, ErrorKind, Fragment, FragmentBuf, Result};
+use crate::executor::{DefaultExecutor, Execute};
+use crate::limiter::{Limiter, Permit};
+use crate::metrics::{Metrics, OperationMetrics};
+use crate::scheduler::{Priority, Scheduler};
+use crate::{
+    BuildCoder, ErasureCode, Error, ErrorKind, Fragment, FragmentBuf, FragmentInfo, FragmentRange,
+    Result, VerifyReport,
+};
 
 thread_local! {
     static ERASURE_CODERS: RefCell<HashMap<String, Box<dyn ErasureCode>>> =
@@ -13,18 +30,213 @@ thread_local! {
 
 /// Thread pool for encoding and decoding data by using an [`ErasureCode`] implementation.
 ///
-/// Internally, this uses [`fibers_tasque::DefaultCpuTaskQueue`] for realizing thread pool functionality.
+/// The tasks are executed by an [`Execute`] implementation.
+/// By default, [`DefaultExecutor`] (i.e., [`fibers_tasque::DefaultCpuTaskQueue`]) is used,
+/// which is shared with the other CPU intensive tasks in the process.
+/// Use [`with_executor`] and [`ThreadPool`] to give the pool its own threads.
 ///
 /// [`ErasureCode`]: ./trait.ErasureCode.html
+/// [`Execute`]: ./executor/trait.Execute.html
+/// [`DefaultExecutor`]: ./executor/struct.DefaultExecutor.html
 /// [`fibers_tasque::DefaultCpuTaskQueue`]: https://docs.rs/fibers_tasque/0.1/fibers_tasque/struct.DefaultCpuTaskQueue.html
-#[derive(Debug, Clone)]
+/// [`with_executor`]: #method.with_executor
+/// [`ThreadPool`]: ./executor/struct.ThreadPool.html
+///
+/// By default, the pool accepts any number of operations.
+/// [`ErasureCoderPoolBuilder`] can be used to limit the operations queued in the pool.
+/// If the limits are exceeded, the futures returned by the pool immediately fail with `ErrorKind::Overloaded`.
+///
+/// If the future returned by an operation is dropped before the operation starts,
+/// the operation is cancelled (see [`cancelled_operations`]).
+/// If an operation panics, its future fails with `ErrorKind::Other`.
+///
+/// [`cancelled_operations`]: #method.cancelled_operations
+/// [`ErasureCoderPoolBuilder`]: ./struct.ErasureCoderPoolBuilder.html
+#[derive(Clone)]
 pub struct ErasureCoderPool<B> {
     builder: B,
+    executor: Arc<dyn Execute>,
+    limiter: Arc<Limiter>,
+    scheduler: Arc<Scheduler>,
+    priority: Priority,
+    deadline: Option<Instant>,
+    timeout: Option<Duration>,
+    cancelled_operations: Arc<AtomicU64>,
+    metrics: Metrics,
+    operation_metrics: Arc<HashMap<&'static str, Arc<OperationMetrics>>>,
 }
 impl<B: BuildCoder> ErasureCoderPool<B> {
-    /// Makes a new `ErasureCoderPool` instance.
+    /// Makes a new `ErasureCoderPool` instance that uses [`DefaultExecutor`].
+    ///
+    /// [`DefaultExecutor`]: ./executor/struct.DefaultExecutor.html
     pub fn new(builder: B) -> Self {
-        ErasureCoderPool { builder }
+        ErasureCoderPoolBuilder::new(builder).build()
+    }
+
+    /// Makes a new `ErasureCoderPool` instance that executes its tasks by using the given executor.
+    pub fn with_executor<E: Execute>(builder: B, executor: E) -> Self {
+        ErasureCoderPoolBuilder::new(builder)
+            .executor(executor)
+            .build()
+    }
+
+    /// Returns a handle of the pool that executes its operations with the given priority.
+    ///
+    /// The returned handle shares the executor and the queue with `self`.
+    /// The priority of the pools made by [`new`] or [`ErasureCoderPoolBuilder`] is `Priority::Foreground`.
+    ///
+    /// [`new`]: #method.new
+    /// [`ErasureCoderPoolBuilder`]: ./struct.ErasureCoderPoolBuilder.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate ecpool;
+    /// # extern crate fibers_global;
+    /// use ecpool::replica::ReplicaCoder;
+    /// use ecpool::{ErasureCoderPool, Priority};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let data_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+    /// let parity_fragments = NonZeroUsize::new(1).ok_or("invalid input")?;
+    /// let pool = ErasureCoderPool::new(ReplicaCoder::new(data_fragments, parity_fragments));
+    /// let encoded = fibers_global::execute(pool.encode(vec![0, 1, 2]))?;
+    ///
+    /// // Repair the lost fragment without slowing down the foreground operations
+    /// let repair = pool.with_priority(Priority::Background);
+    /// let reconstructed = fibers_global::execute(repair.reconstruct(0, encoded[1..].to_vec()))?;
+    /// assert_eq!(reconstructed, encoded[0]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_priority(&self, priority: Priority) -> Self {
+        ErasureCoderPool {
+            priority,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the priority of the operations executed via this handle.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Returns a handle of the pool that gives the given deadline to its operations.
+    ///
+    /// If an operation is still queued when the deadline passes, it is discarded without executing.
+    /// The future of an operation that has not finished by the deadline resolves with `ErrorKind::Timeout`
+    /// (an operation already being executed runs to completion, but its result is dropped).
+    ///
+    /// Note that the futures are notified of the deadline only if they are polled in a [fibers] context.
+    ///
+    /// [fibers]: https://crates.io/crates/fibers
+    pub fn with_deadline(&self, deadline: Instant) -> Self {
+        ErasureCoderPool {
+            deadline: Some(deadline),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a handle of the pool that gives the deadline `timeout` after the submission to each operation.
+    ///
+    /// See [`with_deadline`] for details about deadlines.
+    /// If both a deadline and a timeout are given, the earlier one is used.
+    ///
+    /// [`with_deadline`]: #method.with_deadline
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate ecpool;
+    /// # extern crate fibers_global;
+    /// use ecpool::replica::ReplicaCoder;
+    /// use ecpool::{ErasureCoderPool, ErrorKind};
+    /// use std::num::NonZeroUsize;
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let data_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+    /// let parity_fragments = NonZeroUsize::new(1).ok_or("invalid input")?;
+    /// let pool = ErasureCoderPool::new(ReplicaCoder::new(data_fragments, parity_fragments));
+    ///
+    /// let encoded = fibers_global::execute(pool.with_timeout(Duration::from_secs(10)).encode(vec![0, 1, 2]))?;
+    /// assert_eq!(encoded.len(), 3);
+    ///
+    /// let result = fibers_global::execute(pool.with_timeout(Duration::from_secs(0)).encode(vec![0, 1, 2]));
+    /// assert_eq!(result.map_err(|e| *e.kind()), Err(ErrorKind::Timeout));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        ErasureCoderPool {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Returns `Async::Ready(())` if the pool can accept another operation.
+    ///
+    /// If the pool is full, this returns `Async::NotReady` and
+    /// the current task will be notified when a queued operation finishes.
+    /// Thus, this method must be called in the context of a task (e.g., in `Future::poll`).
+    ///
+    /// Note that the readiness does not reserve any capacity,
+    /// so the subsequent operation may still fail with `ErrorKind::Overloaded` if the pool is shared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate ecpool;
+    /// # extern crate fibers_global;
+    /// # extern crate futures;
+    /// use ecpool::replica::ReplicaCoder;
+    /// use ecpool::ErasureCoderPoolBuilder;
+    /// use futures::{future, Future};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let data_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+    /// let parity_fragments = NonZeroUsize::new(1).ok_or("invalid input")?;
+    /// let pool = ErasureCoderPoolBuilder::new(ReplicaCoder::new(data_fragments, parity_fragments))
+    ///     .max_queued_operations(NonZeroUsize::new(16).ok_or("invalid input")?)
+    ///     .build();
+    ///
+    /// let pool0 = pool.clone();
+    /// let future = future::poll_fn(move || pool0.poll_ready()).and_then(move |()| pool.encode(vec![0, 1, 2]));
+    /// assert_eq!(fibers_global::execute(future)?.len(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn poll_ready(&self) -> Poll<(), Error> {
+        Ok(self.limiter.poll_ready())
+    }
+
+    /// Returns the number of the operations queued in the pool (including the ones being executed).
+    pub fn queued_operations(&self) -> usize {
+        self.limiter.queued_operations()
+    }
+
+    /// Returns the total size of the data and fragments given to the operations queued in the pool.
+    pub fn queued_bytes(&self) -> usize {
+        self.limiter.queued_bytes()
+    }
+
+    /// Returns the metrics of the pool.
+    ///
+    /// See [`Metrics`] for details.
+    ///
+    /// [`Metrics`]: ./metrics/struct.Metrics.html
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Returns the number of the operations that have been cancelled so far.
+    ///
+    /// If the future returned by an operation is dropped before the operation starts,
+    /// the operation is skipped (i.e., cancelled) without executing.
+    pub fn cancelled_operations(&self) -> u64 {
+        self.cancelled_operations.load(Ordering::SeqCst)
     }
 
     /// Encodes the given data to fragments asynchronously.
@@ -38,9 +250,109 @@ impl<B: BuildCoder> ErasureCoderPool<B> {
         T: AsRef<[u8]> + Send + 'static,
     {
         let builder = self.builder.clone();
-        let result = DefaultCpuTaskQueue
-            .async_call(move || Self::with_coder(&builder, |coder| coder.encode(data.as_ref())));
-        LazyResult(result)
+        self.spawn("encode", data.as_ref().len(), move || {
+            Self::with_coder(&builder, |coder| coder.encode(data.as_ref()))
+        })
+    }
+
+    /// Encodes the given data to fragments that share a single contiguous buffer asynchronously.
+    ///
+    /// The encoding process will be executed on a thread in the pool.
+    ///
+    /// Unlike [`encode`], the resulting fragments are [`Bytes`] slices of one allocation,
+    /// so those can be handed to I/O without extra copies.
+    /// If the implementation knows the size of fragments in advance (see [`ErasureCode::fragment_size`]),
+    /// the fragments are directly encoded into the buffer by using [`ErasureCode::encode_into`].
+    ///
+    /// [`encode`]: #method.encode
+    /// [`Bytes`]: https://docs.rs/bytes/1/bytes/struct.Bytes.html
+    /// [`ErasureCode::fragment_size`]: ./trait.ErasureCode.html#method.fragment_size
+    /// [`ErasureCode::encode_into`]: ./trait.ErasureCode.html#method.encode_into
+    #[cfg(feature = "bytes")]
+    pub fn encode_bytes<T>(&self, data: T) -> impl Future<Item = Vec<Bytes>, Error = Error>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn("encode_bytes", data.as_ref().len(), move || {
+            Self::with_coder(&builder, |coder| encode_bytes(coder, data.as_ref()))
+        })
+    }
+
+    /// Returns the size of each fragment that will be produced by encoding data of `data_len` bytes.
+    ///
+    /// This is executed on a thread in the pool but is not counted by the limits of the queue,
+    /// so it is never rejected with `ErrorKind::Overloaded`.
+    ///
+    /// See [`ErasureCode::fragment_size`] for details.
+    ///
+    /// [`ErasureCode::fragment_size`]: ./trait.ErasureCode.html#method.fragment_size
+    pub fn fragment_size(
+        &self,
+        data_len: usize,
+    ) -> impl Future<Item = Option<usize>, Error = Error> {
+        self.query("fragment_size", move |coder| {
+            Ok(coder.fragment_size(data_len))
+        })
+    }
+
+    /// Encodes the given data into the given buffers asynchronously.
+    ///
+    /// The encoding process will be executed on a thread in the pool.
+    /// The buffers are returned after they are filled with the fragments.
+    ///
+    /// See [`ErasureCode::encode_into`] for details.
+    ///
+    /// [`ErasureCode::encode_into`]: ./trait.ErasureCode.html#method.encode_into
+    pub fn encode_into<T, U>(
+        &self,
+        data: T,
+        mut buffers: Vec<U>,
+    ) -> impl Future<Item = Vec<U>, Error = Error>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+        U: AsMut<[u8]> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn("encode_into", data.as_ref().len(), move || {
+            {
+                let mut slices = buffers.iter_mut().map(|b| b.as_mut()).collect::<Vec<_>>();
+                Self::with_coder(&builder, |coder| {
+                    coder.encode_into(data.as_ref(), &mut slices)
+                })?;
+            }
+            Ok(buffers)
+        })
+    }
+
+    /// Computes the parity fragments that correspond to the updated data fragment asynchronously.
+    ///
+    /// The computation will be executed on a thread in the pool.
+    ///
+    /// See [`ErasureCode::update_parity`] for details.
+    ///
+    /// [`ErasureCode::update_parity`]: ./trait.ErasureCode.html#method.update_parity
+    pub fn update_parity<T>(
+        &self,
+        data_index: usize,
+        old_data: T,
+        new_data: T,
+        parities: Vec<T>,
+    ) -> impl Future<Item = Vec<FragmentBuf>, Error = Error>
+    where
+        T: AsRef<Fragment> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn(
+            "update_parity",
+            old_data.as_ref().len() + new_data.as_ref().len() + total_len(&parities),
+            move || {
+                let parities = parities.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+                Self::with_coder(&builder, |coder| {
+                    coder.update_parity(data_index, old_data.as_ref(), new_data.as_ref(), &parities)
+                })
+            },
+        )
     }
 
     /// Decodes the original data from the given fragments asynchronously.
@@ -53,11 +365,83 @@ impl<B: BuildCoder> ErasureCoderPool<B> {
         T: AsRef<Fragment> + Send + 'static,
     {
         let builder = self.builder.clone();
-        let result = DefaultCpuTaskQueue.async_call(move || {
+        self.spawn("decode", total_len(&fragments), move || {
             let fragments = fragments.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
             Self::with_coder(&builder, |coder| coder.decode(&fragments))
-        });
-        LazyResult(result)
+        })
+    }
+
+    /// Decodes the given range of the original data from the given fragments asynchronously.
+    ///
+    /// The decoding process will be executed on a thread in the pool.
+    ///
+    /// See [`ErasureCode::decode_range`] for details.
+    ///
+    /// [`ErasureCode::decode_range`]: ./trait.ErasureCode.html#method.decode_range
+    pub fn decode_range<T>(
+        &self,
+        range: Range<usize>,
+        fragments: Vec<T>,
+    ) -> impl Future<Item = Vec<u8>, Error = Error>
+    where
+        T: AsRef<Fragment> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn("decode_range", total_len(&fragments), move || {
+            let fragments = fragments.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+            Self::with_coder(&builder, |coder| coder.decode_range(range, &fragments))
+        })
+    }
+
+    /// Returns the byte ranges of the fragments that hold the given range of the original data.
+    ///
+    /// This is executed on a thread in the pool but is not counted by the limits of the queue,
+    /// so it is never rejected with `ErrorKind::Overloaded`.
+    ///
+    /// See [`ErasureCode::fragment_ranges`] for details.
+    ///
+    /// [`ErasureCode::fragment_ranges`]: ./trait.ErasureCode.html#method.fragment_ranges
+    pub fn fragment_ranges(
+        &self,
+        range: Range<usize>,
+        data_len: usize,
+    ) -> impl Future<Item = Option<Vec<FragmentRange>>, Error = Error> {
+        self.query("fragment_ranges", move |coder| {
+            Ok(coder.fragment_ranges(range, data_len))
+        })
+    }
+
+    /// Returns the metadata of the given fragment asynchronously.
+    ///
+    /// See [`ErasureCode::fragment_info`] for details.
+    ///
+    /// [`ErasureCode::fragment_info`]: ./trait.ErasureCode.html#method.fragment_info
+    pub fn fragment_info<T>(&self, fragment: T) -> impl Future<Item = FragmentInfo, Error = Error>
+    where
+        T: AsRef<Fragment> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn("fragment_info", fragment.as_ref().len(), move || {
+            Self::with_coder(&builder, |coder| coder.fragment_info(fragment.as_ref()))
+        })
+    }
+
+    /// Verifies the given fragments asynchronously.
+    ///
+    /// The verification process will be executed on a thread in the pool.
+    ///
+    /// See [`ErasureCode::verify`] for details.
+    ///
+    /// [`ErasureCode::verify`]: ./trait.ErasureCode.html#method.verify
+    pub fn verify<T>(&self, fragments: Vec<T>) -> impl Future<Item = VerifyReport, Error = Error>
+    where
+        T: AsRef<Fragment> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn("verify", total_len(&fragments), move || {
+            let fragments = fragments.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+            Self::with_coder(&builder, |coder| Ok(coder.verify(&fragments)))
+        })
     }
 
     /// Reconstructs the fragment specified by the given index from other fragments asynchronously.
@@ -72,11 +456,312 @@ impl<B: BuildCoder> ErasureCoderPool<B> {
         T: AsRef<Fragment> + Send + 'static,
     {
         let builder = self.builder.clone();
-        let result = DefaultCpuTaskQueue.async_call(move || {
+        self.spawn("reconstruct", total_len(&fragments), move || {
             let fragments = fragments.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
             Self::with_coder(&builder, |coder| coder.reconstruct(index, &fragments))
-        });
-        LazyResult(result)
+        })
+    }
+
+    /// Reconstructs the fragments specified by the given indices from other fragments asynchronously.
+    ///
+    /// The reconstruction process will be executed on a thread in the pool.
+    ///
+    /// The result vector contains the reconstructed fragments in the same order as `indices`.
+    pub fn reconstruct_many<T>(
+        &self,
+        indices: Vec<usize>,
+        fragments: Vec<T>,
+    ) -> impl Future<Item = Vec<FragmentBuf>, Error = Error>
+    where
+        T: AsRef<Fragment> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn("reconstruct_many", total_len(&fragments), move || {
+            let fragments = fragments.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+            Self::with_coder(&builder, |coder| {
+                coder.reconstruct_many(&indices, &fragments)
+            })
+        })
+    }
+
+    /// Decodes the original data from the given fragments and their indices asynchronously.
+    ///
+    /// The decoding process will be executed on a thread in the pool.
+    ///
+    /// See [`ErasureCode::decode_indexed`] for details.
+    ///
+    /// [`ErasureCode::decode_indexed`]: ./trait.ErasureCode.html#method.decode_indexed
+    pub fn decode_indexed<T>(
+        &self,
+        fragments: Vec<(usize, T)>,
+    ) -> impl Future<Item = Vec<u8>, Error = Error>
+    where
+        T: AsRef<Fragment> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn(
+            "decode_indexed",
+            total_len(fragments.iter().map(|(_, f)| f)),
+            move || {
+                let fragments = fragments
+                    .iter()
+                    .map(|(i, f)| (*i, f.as_ref()))
+                    .collect::<Vec<_>>();
+                Self::with_coder(&builder, |coder| coder.decode_indexed(&fragments))
+            },
+        )
+    }
+
+    /// Reconstructs the fragment specified by the given index from other fragments
+    /// and their indices asynchronously.
+    ///
+    /// The reconstruction process will be executed on a thread in the pool.
+    ///
+    /// See [`ErasureCode::reconstruct_indexed`] for details.
+    ///
+    /// [`ErasureCode::reconstruct_indexed`]: ./trait.ErasureCode.html#method.reconstruct_indexed
+    pub fn reconstruct_indexed<T>(
+        &self,
+        index: usize,
+        fragments: Vec<(usize, T)>,
+    ) -> impl Future<Item = Vec<u8>, Error = Error>
+    where
+        T: AsRef<Fragment> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn(
+            "reconstruct_indexed",
+            total_len(fragments.iter().map(|(_, f)| f)),
+            move || {
+                let fragments = fragments
+                    .iter()
+                    .map(|(i, f)| (*i, f.as_ref()))
+                    .collect::<Vec<_>>();
+                Self::with_coder(&builder, |coder| {
+                    coder.reconstruct_indexed(index, &fragments)
+                })
+            },
+        )
+    }
+
+    /// Returns `true` if the original data can be decoded from the available fragments.
+    ///
+    /// This is executed on a thread in the pool but is not counted by the limits of the queue,
+    /// so it is never rejected with `ErrorKind::Overloaded`.
+    ///
+    /// See [`ErasureCode::is_decodable`] for details.
+    ///
+    /// [`ErasureCode::is_decodable`]: ./trait.ErasureCode.html#method.is_decodable
+    pub fn is_decodable(&self, available: Vec<usize>) -> impl Future<Item = bool, Error = Error> {
+        self.query("is_decodable", move |coder| {
+            Ok(coder.is_decodable(&available))
+        })
+    }
+
+    /// Returns the indices of the fragments to be read for decoding the original data.
+    ///
+    /// This is executed on a thread in the pool but is not counted by the limits of the queue,
+    /// so it is never rejected with `ErrorKind::Overloaded`.
+    ///
+    /// See [`ErasureCode::decode_plan`] for details.
+    ///
+    /// [`ErasureCode::decode_plan`]: ./trait.ErasureCode.html#method.decode_plan
+    pub fn decode_plan(
+        &self,
+        available: Vec<usize>,
+        costs: Option<Vec<u64>>,
+    ) -> impl Future<Item = Vec<usize>, Error = Error> {
+        self.query("decode_plan", move |coder| {
+            coder.decode_plan(&available, costs.as_ref().map(|c| &c[..]))
+        })
+    }
+
+    /// Returns the indices of the fragments to be read for reconstructing
+    /// the fragment specified by the given index.
+    ///
+    /// This is executed on a thread in the pool but is not counted by the limits of the queue,
+    /// so it is never rejected with `ErrorKind::Overloaded`.
+    ///
+    /// See [`ErasureCode::reconstruct_plan`] for details.
+    ///
+    /// [`ErasureCode::reconstruct_plan`]: ./trait.ErasureCode.html#method.reconstruct_plan
+    pub fn reconstruct_plan(
+        &self,
+        index: usize,
+        available: Vec<usize>,
+        costs: Option<Vec<u64>>,
+    ) -> impl Future<Item = Vec<usize>, Error = Error> {
+        self.query("reconstruct_plan", move |coder| {
+            coder.reconstruct_plan(index, &available, costs.as_ref().map(|c| &c[..]))
+        })
+    }
+
+    /// Returns the byte ranges of the fragments that are required to reconstruct
+    /// the fragment specified by the given index.
+    ///
+    /// This is executed on a thread in the pool but is not counted by the limits of the queue,
+    /// so it is never rejected with `ErrorKind::Overloaded`.
+    ///
+    /// See [`ErasureCode::repair_plan`] for details.
+    ///
+    /// [`ErasureCode::repair_plan`]: ./trait.ErasureCode.html#method.repair_plan
+    pub fn repair_plan(
+        &self,
+        index: usize,
+        available: Vec<usize>,
+        fragment_len: usize,
+    ) -> impl Future<Item = Vec<FragmentRange>, Error = Error> {
+        self.query("repair_plan", move |coder| {
+            coder.repair_plan(index, &available, fragment_len)
+        })
+    }
+
+    /// Reconstructs the fragment specified by the given index from
+    /// the byte ranges of other fragments asynchronously.
+    ///
+    /// The reconstruction process will be executed on a thread in the pool.
+    ///
+    /// See [`ErasureCode::reconstruct_partial`] for details.
+    ///
+    /// [`ErasureCode::reconstruct_partial`]: ./trait.ErasureCode.html#method.reconstruct_partial
+    pub fn reconstruct_partial<T>(
+        &self,
+        index: usize,
+        parts: Vec<(FragmentRange, T)>,
+    ) -> impl Future<Item = Vec<u8>, Error = Error>
+    where
+        T: AsRef<Fragment> + Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.spawn(
+            "reconstruct_partial",
+            total_len(parts.iter().map(|(_, f)| f)),
+            move || {
+                let parts = parts
+                    .iter()
+                    .map(|(r, f)| (r.clone(), f.as_ref()))
+                    .collect::<Vec<_>>();
+                Self::with_coder(&builder, |coder| coder.reconstruct_partial(index, &parts))
+            },
+        )
+    }
+
+    fn spawn<F, T>(&self, operation: &'static str, bytes: usize, f: F) -> LazyResult<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        match track!(self.limiter.acquire(bytes)) {
+            Ok(permit) => self.enqueue(operation, bytes, Some(permit), f),
+            Err(e) => {
+                self.operation_metrics(operation).failed(*e.kind());
+                LazyResult::Failed(Some(e))
+            }
+        }
+    }
+
+    /// Executes a cheap operation (e.g., a plan) on a thread in the pool.
+    ///
+    /// Unlike `spawn`, the operation is not counted by the limits of the queue,
+    /// so it is never rejected with `ErrorKind::Overloaded`.
+    fn query<F, T>(&self, operation: &'static str, f: F) -> LazyResult<T>
+    where
+        for<'a> F: FnOnce(&'a mut dyn ErasureCode) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let builder = self.builder.clone();
+        self.enqueue(operation, 0, None, move || Self::with_coder(&builder, f))
+    }
+
+    fn enqueue<F, T>(
+        &self,
+        operation: &'static str,
+        bytes: usize,
+        permit: Option<Permit>,
+        f: F,
+    ) -> LazyResult<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let metrics = self.operation_metrics(operation);
+        let deadline = self.deadline();
+        let queued_at = Instant::now();
+        metrics.queued();
+        let (tx, rx) = oneshot::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let is_cancelled = Arc::clone(&cancelled);
+        let cancelled_operations = Arc::clone(&self.cancelled_operations);
+        self.scheduler.push(
+            self.priority,
+            Box::new(move || {
+                metrics.dequeued(queued_at.elapsed());
+                if let Some(e) = deadline.and_then(check_deadline) {
+                    metrics.failed(*e.kind());
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+                if is_cancelled.load(Ordering::SeqCst) {
+                    metrics.cancelled();
+                    cancelled_operations.fetch_add(1, Ordering::SeqCst);
+                    return;
+                }
+
+                metrics.started();
+                let started_at = Instant::now();
+                // The panics are reported as errors so that the metrics and the caller can observe them
+                let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|e| {
+                    let message = e
+                        .downcast_ref::<&str>()
+                        .map(|m| (*m).to_owned())
+                        .or_else(|| e.downcast_ref::<String>().cloned())
+                        .unwrap_or_default();
+                    let e = ErrorKind::Other.cause(format!("The operation panicked: {}", message));
+                    Err(track!(Error::from(e)))
+                });
+                let error = result.as_ref().err().map(|e| *e.kind());
+                metrics.finished(started_at.elapsed(), bytes, error);
+                drop(permit);
+                let _ = tx.send(result);
+            }),
+        );
+        let scheduler = Arc::clone(&self.scheduler);
+        self.executor.execute(Box::new(move || scheduler.run()));
+        LazyResult::Waiting {
+            rx,
+            cancelled,
+            deadline: deadline.map(|d| {
+                (
+                    d,
+                    timer::timeout(d.saturating_duration_since(Instant::now())),
+                )
+            }),
+        }
+    }
+
+    /// Returns the numbers of the data fragments and all the fragments of the coder asynchronously.
+    pub(crate) fn fragment_counts(
+        &self,
+    ) -> impl Future<Item = (NonZeroUsize, NonZeroUsize), Error = Error> {
+        self.query("fragment_counts", |coder| {
+            Ok((coder.data_fragments(), coder.fragments()))
+        })
+    }
+
+    fn operation_metrics(&self, operation: &'static str) -> Arc<OperationMetrics> {
+        Arc::clone(
+            self.operation_metrics
+                .get(operation)
+                .expect("Unregistered operation"),
+        )
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        let timeout = self.timeout.map(|t| Instant::now() + t);
+        match (self.deadline, timeout) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
     }
 
     fn with_coder<F, T>(builder: &B, f: F) -> Result<T>
@@ -95,17 +780,252 @@ impl<B: BuildCoder> ErasureCoderPool<B> {
     }
 }
 
-struct LazyResult<T>(AsyncCall<Result<T>>);
+#[cfg(feature = "bytes")]
+fn encode_bytes(coder: &mut dyn ErasureCode, data: &[u8]) -> Result<Vec<Bytes>> {
+    let fragments = coder.fragments().get();
+    let (buf, sizes) = if let Some(size) = coder.fragment_size(data.len()) {
+        let mut buf = BytesMut::zeroed(size * fragments);
+        let mut rest = &mut buf[..];
+        let mut buffers = Vec::with_capacity(fragments);
+        for _ in 0..fragments {
+            let (buffer, tail) = rest.split_at_mut(size);
+            buffers.push(buffer);
+            rest = tail;
+        }
+        track!(coder.encode_into(data, &mut buffers))?;
+        (buf, vec![size; fragments])
+    } else {
+        let encoded = track!(coder.encode(data))?;
+        let mut buf = BytesMut::with_capacity(encoded.iter().map(|f| f.len()).sum());
+        for fragment in &encoded {
+            buf.extend_from_slice(fragment);
+        }
+        (buf, encoded.iter().map(|f| f.len()).collect())
+    };
+
+    let buf = buf.freeze();
+    let mut offset = 0;
+    Ok(sizes
+        .into_iter()
+        .map(|size| {
+            offset += size;
+            buf.slice(offset - size..offset)
+        })
+        .collect())
+}
+
+impl<B: fmt::Debug> fmt::Debug for ErasureCoderPool<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ErasureCoderPool")
+            .field("builder", &self.builder)
+            .field("limiter", &self.limiter)
+            .field("scheduler", &self.scheduler)
+            .field("priority", &self.priority)
+            .field("deadline", &self.deadline)
+            .field("timeout", &self.timeout)
+            .field("cancelled_operations", &self.cancelled_operations)
+            .field("metrics", &self.metrics)
+            .finish()
+    }
+}
+
+/// [`ErasureCoderPool`] builder.
+///
+/// [`ErasureCoderPool`]: ./struct.ErasureCoderPool.html
+#[derive(Clone)]
+pub struct ErasureCoderPoolBuilder<B> {
+    builder: B,
+    executor: Arc<dyn Execute>,
+    max_queued_operations: Option<NonZeroUsize>,
+    max_queued_bytes: Option<NonZeroUsize>,
+    max_background_threads: Option<NonZeroUsize>,
+    metrics: Metrics,
+}
+impl<B: BuildCoder> ErasureCoderPoolBuilder<B> {
+    /// Makes a new `ErasureCoderPoolBuilder` instance with the default settings.
+    pub fn new(builder: B) -> Self {
+        ErasureCoderPoolBuilder {
+            builder,
+            executor: Arc::new(DefaultExecutor),
+            max_queued_operations: None,
+            max_queued_bytes: None,
+            max_background_threads: None,
+            metrics: Metrics::new(),
+        }
+    }
+
+    /// Sets the executor of the pool.
+    ///
+    /// The default value is [`DefaultExecutor`].
+    ///
+    /// [`DefaultExecutor`]: ./executor/struct.DefaultExecutor.html
+    pub fn executor<E: Execute>(mut self, executor: E) -> Self {
+        self.executor = Arc::new(executor);
+        self
+    }
+
+    /// Sets the maximum number of the operations queued in the pool (including the ones being executed).
+    ///
+    /// If omitted, the number is unlimited.
+    pub fn max_queued_operations(mut self, max: NonZeroUsize) -> Self {
+        self.max_queued_operations = Some(max);
+        self
+    }
+
+    /// Sets the maximum total size of the data and fragments given to the operations queued in the pool.
+    ///
+    /// An operation larger than the limit is accepted only if no other operations are queued.
+    ///
+    /// If omitted, the size is unlimited.
+    pub fn max_queued_bytes(mut self, max: NonZeroUsize) -> Self {
+        self.max_queued_bytes = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of the threads that execute the operations other than `Priority::Foreground` concurrently.
+    ///
+    /// This reserves the rest of the threads of the executor for the foreground operations.
+    ///
+    /// If omitted, the number is unlimited.
+    pub fn max_background_threads(mut self, max: NonZeroUsize) -> Self {
+        self.max_background_threads = Some(max);
+        self
+    }
+
+    /// Sets the registry of the metrics of the pool.
+    ///
+    /// A registry can be shared by multiple pools (e.g., to export the metrics of all coders at once).
+    ///
+    /// If omitted, a new registry is used.
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Builds an `ErasureCoderPool` instance.
+    pub fn build(self) -> ErasureCoderPool<B> {
+        let coder_id = self.builder.coder_id();
+        let operation_metrics = OPERATIONS
+            .iter()
+            .map(|&operation| {
+                (
+                    operation,
+                    self.metrics.operation(coder_id.clone(), operation),
+                )
+            })
+            .collect();
+        ErasureCoderPool {
+            builder: self.builder,
+            executor: self.executor,
+            limiter: Arc::new(Limiter::new(
+                self.max_queued_operations,
+                self.max_queued_bytes,
+            )),
+            scheduler: Arc::new(Scheduler::new(self.max_background_threads)),
+            priority: Priority::default(),
+            deadline: None,
+            timeout: None,
+            cancelled_operations: Arc::default(),
+            metrics: self.metrics,
+            operation_metrics: Arc::new(operation_metrics),
+        }
+    }
+}
+impl<B: fmt::Debug> fmt::Debug for ErasureCoderPoolBuilder<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ErasureCoderPoolBuilder")
+            .field("builder", &self.builder)
+            .field("max_queued_operations", &self.max_queued_operations)
+            .field("max_queued_bytes", &self.max_queued_bytes)
+            .field("max_background_threads", &self.max_background_threads)
+            .finish()
+    }
+}
+
+/// The names of the operations executed via the queue of a pool.
+///
+/// The metrics of those are registered when the pool is built, so that `spawn` does not need to look up the registry.
+const OPERATIONS: [&str; 20] = [
+    "encode",
+    "encode_bytes",
+    "encode_into",
+    "update_parity",
+    "decode",
+    "decode_range",
+    "decode_indexed",
+    "fragment_info",
+    "verify",
+    "reconstruct",
+    "reconstruct_many",
+    "reconstruct_indexed",
+    "reconstruct_partial",
+    "fragment_size",
+    "fragment_ranges",
+    "is_decodable",
+    "decode_plan",
+    "reconstruct_plan",
+    "repair_plan",
+    "fragment_counts",
+];
+
+fn total_len<I>(fragments: I) -> usize
+where
+    I: IntoIterator,
+    I::Item: AsRef<Fragment>,
+{
+    fragments.into_iter().map(|f| f.as_ref().len()).sum()
+}
+
+/// Returns an `ErrorKind::Timeout` error if the given deadline has passed.
+fn check_deadline(deadline: Instant) -> Option<Error> {
+    let now = Instant::now();
+    if now < deadline {
+        return None;
+    }
+    let e = ErrorKind::Timeout.cause(format!(
+        "The deadline has passed: exceeded={:?}",
+        now - deadline
+    ));
+    Some(track!(Error::from(e)))
+}
+
+/// The future of an operation.
+///
+/// If this is dropped before the operation starts, the operation is cancelled.
+enum LazyResult<T> {
+    Waiting {
+        rx: oneshot::Receiver<Result<T>>,
+        cancelled: Arc<AtomicBool>,
+        deadline: Option<(Instant, Timeout)>,
+    },
+    Failed(Option<Error>),
+}
 impl<T> Future for LazyResult<T> {
     type Item = T;
     type Error = Error;
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        if let Async::Ready(result) = track!(self.0.poll().map_err(|e| ErrorKind::Other.cause(e)))?
-        {
+        let (rx, deadline) = match self {
+            LazyResult::Waiting { rx, deadline, .. } => (rx, deadline),
+            LazyResult::Failed(e) => return Err(e.take().expect("Cannot poll twice")),
+        };
+        if let Async::Ready(result) = track!(rx.poll().map_err(|e| ErrorKind::Other.cause(e)))? {
             let value = result?;
-            Ok(Async::Ready(value))
-        } else {
-            Ok(Async::NotReady)
+            return Ok(Async::Ready(value));
+        }
+        if let Some((deadline, timer)) = deadline {
+            // The timer is used only for being notified of the deadline
+            let _ = timer.poll();
+            if let Some(e) = check_deadline(*deadline) {
+                return Err(track!(e));
+            }
+        }
+        Ok(Async::NotReady)
+    }
+}
+impl<T> Drop for LazyResult<T> {
+    fn drop(&mut self) {
+        if let LazyResult::Waiting { cancelled, .. } = self {
+            cancelled.store(true, Ordering::SeqCst);
         }
     }
 }
@@ -114,12 +1034,38 @@ impl<T> Future for LazyResult<T> {
 mod tests {
     use std::num::NonZeroUsize;
     use std::result::Result;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Mutex, MutexGuard};
     use trackable::error::{Failed, MainError};
 
     use super::*;
+    use crate::executor::{Task, ThreadPool, ThreadPoolBuilder};
+    use crate::reedsolomon::ReedSolomonCoder;
     use crate::replica::ReplicaCoder;
+    use crate::test_util::test_data;
     use crate::ErrorKind;
 
+    /// An executor that runs the tasks only when `run_all` is called.
+    #[derive(Clone, Default)]
+    struct ManualExecutor(Arc<Mutex<Vec<Task>>>);
+    impl ManualExecutor {
+        fn run_all(&self) {
+            let tasks = std::mem::take(&mut *self.lock());
+            for task in tasks {
+                task();
+            }
+        }
+
+        fn lock(&self) -> MutexGuard<'_, Vec<Task>> {
+            self.0.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+    impl Execute for ManualExecutor {
+        fn execute(&self, task: Task) {
+            self.lock().push(task);
+        }
+    }
+
     #[test]
     fn pool_works() -> Result<(), MainError> {
         let data_fragments = track_assert_some!(NonZeroUsize::new(4), Failed);
@@ -154,4 +1100,218 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn with_executor_works() -> Result<(), MainError> {
+        #[derive(Clone)]
+        struct CountingExecutor(ThreadPool, Arc<AtomicUsize>);
+        impl Execute for CountingExecutor {
+            fn execute(&self, task: Task) {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.execute(task);
+            }
+        }
+
+        let data_fragments = track_assert_some!(NonZeroUsize::new(4), Failed);
+        let parity_fragments = track_assert_some!(NonZeroUsize::new(2), Failed);
+        let threads = track_assert_some!(NonZeroUsize::new(1), Failed);
+        let executor = CountingExecutor(
+            track!(ThreadPoolBuilder::new().threads(threads).build())?,
+            Arc::default(),
+        );
+
+        let coder = ErasureCoderPool::with_executor(
+            track!(ReedSolomonCoder::new(data_fragments, parity_fragments))?,
+            executor.clone(),
+        );
+        let data = test_data(103);
+        let encoded = track!(fibers_global::execute(coder.encode(data.clone())))?;
+        let decoded = track!(fibers_global::execute(coder.decode(encoded[2..].to_vec())))?;
+        assert_eq!(decoded, data);
+        assert_eq!(executor.1.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn overloaded_pool_rejects_operations() -> Result<(), MainError> {
+        let data_fragments = track_assert_some!(NonZeroUsize::new(4), Failed);
+        let parity_fragments = track_assert_some!(NonZeroUsize::new(2), Failed);
+        let max = track_assert_some!(NonZeroUsize::new(2), Failed);
+        let executor = ManualExecutor::default();
+        let coder =
+            ErasureCoderPoolBuilder::new(ReplicaCoder::new(data_fragments, parity_fragments))
+                .executor(executor.clone())
+                .max_queued_operations(max)
+                .build();
+
+        let encode0 = coder.encode(vec![0, 1, 2, 3]);
+        let encode1 = coder.encode(vec![4, 5, 6, 7]);
+        assert_eq!(coder.queued_operations(), 2);
+        assert_eq!(coder.queued_bytes(), 8);
+        assert_eq!(
+            Err(ErrorKind::Overloaded),
+            fibers_global::execute(coder.encode(vec![])).map_err(|e| *e.kind())
+        );
+
+        // The plans are not rejected even if the pool is full
+        let plan = coder.decode_plan(vec![1, 2, 3, 4, 5], None);
+        assert_eq!(coder.queued_operations(), 2);
+
+        executor.run_all();
+        assert_eq!(coder.queued_operations(), 0);
+        track!(fibers_global::execute(encode0))?;
+        track!(fibers_global::execute(encode1))?;
+        assert_eq!(track!(fibers_global::execute(plan))?, [1, 2, 3, 4]);
+
+        // The operations of the dropped futures remain queued until the executor skips them
+        let _ = coder.encode(vec![]);
+        assert_eq!(coder.queued_operations(), 1);
+        executor.run_all();
+        assert_eq!(coder.queued_operations(), 0);
+        assert_eq!(coder.cancelled_operations(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn deadline_works() -> Result<(), MainError> {
+        let data_fragments = track_assert_some!(NonZeroUsize::new(4), Failed);
+        let parity_fragments = track_assert_some!(NonZeroUsize::new(2), Failed);
+        let executor = ManualExecutor::default();
+        let coder = ErasureCoderPool::with_executor(
+            ReplicaCoder::new(data_fragments, parity_fragments),
+            executor.clone(),
+        );
+
+        // The future resolves at the deadline even if the operation is still queued
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let encode = coder.with_deadline(deadline).encode(vec![0, 1, 2, 3]);
+        assert_eq!(
+            Err(ErrorKind::Timeout),
+            fibers_global::execute(encode).map_err(|e| *e.kind())
+        );
+        assert!(Instant::now() >= deadline);
+        assert_eq!(coder.queued_operations(), 1);
+
+        executor.run_all();
+        assert_eq!(coder.queued_operations(), 0);
+        assert_eq!(coder.cancelled_operations(), 0);
+
+        let encode = coder
+            .with_timeout(Duration::from_secs(60))
+            .encode(vec![0, 1, 2, 3]);
+        executor.run_all();
+        track!(fibers_global::execute(encode))?;
+        Ok(())
+    }
+
+    #[test]
+    fn metrics_works() -> Result<(), MainError> {
+        let data_fragments = track_assert_some!(NonZeroUsize::new(4), Failed);
+        let parity_fragments = track_assert_some!(NonZeroUsize::new(2), Failed);
+        let metrics = Metrics::new();
+        let coder =
+            ErasureCoderPoolBuilder::new(ReplicaCoder::new(data_fragments, parity_fragments))
+                .metrics(metrics.clone())
+                .build();
+
+        let encoded = track!(fibers_global::execute(coder.encode(vec![0, 1, 2, 3])))?;
+        assert!(fibers_global::execute(coder.decode(encoded[3..].to_vec())).is_err());
+        track!(fibers_global::execute(coder.decode(encoded[2..].to_vec())))?;
+
+        let snapshot = metrics.snapshot();
+        let encode = track_assert_some!(snapshot.get("replica:4:2", "encode"), Failed);
+        assert_eq!(encode.completed, 1);
+        assert_eq!(encode.bytes, 4);
+        let decode = track_assert_some!(snapshot.get("replica:4:2", "decode"), Failed);
+        assert_eq!(decode.completed, 1);
+        assert_eq!(decode.failed, [(ErrorKind::InvalidInput, 1)]);
+        assert_eq!((decode.queued, decode.running), (0, 0));
+        assert_eq!(decode.wait_time.count, 2);
+        assert_eq!(decode.execution_time.count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn panicked_operation_is_reported() -> Result<(), MainError> {
+        struct PanicBuffer;
+        impl AsMut<[u8]> for PanicBuffer {
+            fn as_mut(&mut self) -> &mut [u8] {
+                panic!("foo")
+            }
+        }
+
+        let data_fragments = track_assert_some!(NonZeroUsize::new(4), Failed);
+        let parity_fragments = track_assert_some!(NonZeroUsize::new(2), Failed);
+        let coder = ErasureCoderPool::new(ReplicaCoder::new(data_fragments, parity_fragments));
+
+        let buffers = (0..6).map(|_| PanicBuffer).collect::<Vec<_>>();
+        let e = track_assert_some!(
+            fibers_global::execute(coder.encode_into(vec![0, 1, 2, 3], buffers)).err(),
+            Failed
+        );
+        assert_eq!(*e.kind(), ErrorKind::Other);
+        assert!(e.to_string().contains("panicked: foo"), "{}", e);
+
+        let snapshot = coder.metrics().snapshot();
+        let encode_into = track_assert_some!(snapshot.get("replica:4:2", "encode_into"), Failed);
+        assert_eq!(encode_into.failed, [(ErrorKind::Other, 1)]);
+        assert_eq!(encode_into.running, 0);
+        Ok(())
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn encode_bytes_works() -> Result<(), MainError> {
+        let data_fragments = track_assert_some!(NonZeroUsize::new(4), Failed);
+        let parity_fragments = track_assert_some!(NonZeroUsize::new(2), Failed);
+
+        let coder = ErasureCoderPool::new(track!(ReedSolomonCoder::new(
+            data_fragments,
+            parity_fragments
+        ))?);
+        let data = test_data(103);
+        let encoded = track!(fibers_global::execute(coder.encode_bytes(data.clone())))?;
+        assert_eq!(
+            encoded,
+            track!(fibers_global::execute(coder.encode(data.clone())))?
+        );
+        for (a, b) in encoded.iter().zip(encoded.iter().skip(1)) {
+            assert_eq!(a.as_ptr().wrapping_add(a.len()), b.as_ptr());
+        }
+
+        let decoded = track!(fibers_global::execute(coder.decode(encoded[2..].to_vec())))?;
+        assert_eq!(decoded, data);
+        Ok(())
+    }
+
+    #[test]
+    fn encode_into_works() -> Result<(), MainError> {
+        let data_fragments = track_assert_some!(NonZeroUsize::new(4), Failed);
+        let parity_fragments = track_assert_some!(NonZeroUsize::new(2), Failed);
+
+        let coder = ErasureCoderPool::new(track!(ReedSolomonCoder::new(
+            data_fragments,
+            parity_fragments
+        ))?);
+        let data = test_data(103);
+        let size = track!(fibers_global::execute(coder.fragment_size(data.len())))?;
+        let size = track_assert_some!(size, Failed);
+
+        // The buffers may contain garbage
+        let buffers = vec![vec![0xFF; size]; 6];
+        let encoded = track!(fibers_global::execute(
+            coder.encode_into(data.clone(), buffers)
+        ))?;
+        assert_eq!(
+            encoded,
+            track!(fibers_global::execute(coder.encode(data.clone())))?
+        );
+
+        let buffers = vec![vec![0; size + 1]; 6];
+        assert_eq!(
+            Err(ErrorKind::InvalidInput),
+            fibers_global::execute(coder.encode_into(data, buffers)).map_err(|e| *e.kind())
+        );
+        Ok(())
+    }
 }
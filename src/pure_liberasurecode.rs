@@ -0,0 +1,1284 @@
+//! A pure-Rust [`ErasureCode`] implementation that is byte-compatible with [openstack/liberasurecode].
+//!
+//! The fragments produced by [`PureLibErasureCoder`] have the same layout
+//! (i.e., liberasurecode's fragment header followed by the payload) and the same parity math
+//! as the ones produced by liberasurecode-1.5.0 with the `jerasure_rs_vand` (`w = 32`) or
+//! `isa_l_rs_vand` backend.
+//! So the fragments written via [`LibErasureCoder`] can be decoded on hosts
+//! where liberasurecode is unavailable, and vice versa.
+//!
+//! [`ErasureCode`]: ../trait.ErasureCode.html
+//! [`PureLibErasureCoder`]: ./struct.PureLibErasureCoder.html
+//! [`LibErasureCoder`]: ../liberasurecode/struct.LibErasureCoder.html
+//! [openstack/liberasurecode]: https://github.com/openstack/liberasurecode
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::crc32;
+use crate::gf256;
+use crate::gf2p32;
+use crate::plan::{self, FragmentRange};
+use crate::verify::{self, FragmentMeta};
+use crate::{
+    BuildCoder, ErasureCode, Error, ErrorKind, Fragment, FragmentBuf, FragmentInfo, Result,
+    VerifyReport,
+};
+
+/// Erasure coding backends supported by [`PureLibErasureCoder`].
+///
+/// [`PureLibErasureCoder`]: ./struct.PureLibErasureCoder.html
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Backend {
+    /// Reed-Solomon coding based on Vandermonde matrix of [jerasure] (`w = 32`).
+    ///
+    /// [jerasure]: https://github.com/ceph/jerasure
+    #[default]
+    JerasureRsVand,
+
+    /// Reed-Solomon coding based on Vandermonde matrix of [Intel ISA-L].
+    ///
+    /// [Intel ISA-L]: https://github.com/intel/isa-l
+    IsaLRsVand,
+}
+impl Backend {
+    /// Returns the identifier of the backend stored in fragment headers.
+    pub fn id(self) -> u8 {
+        match self {
+            Backend::JerasureRsVand => 1,
+            Backend::IsaLRsVand => 4,
+        }
+    }
+
+    fn version(self) -> u32 {
+        match self {
+            Backend::JerasureRsVand => version(2, 0, 0),
+            Backend::IsaLRsVand => version(2, 13, 0),
+        }
+    }
+
+    fn word_size(self) -> usize {
+        match self {
+            Backend::JerasureRsVand => 4,
+            Backend::IsaLRsVand => 1,
+        }
+    }
+
+    fn mul_slice_xor(self, c: u32, input: &[u8], output: &mut [u8]) {
+        match self {
+            Backend::JerasureRsVand => gf2p32::mul_slice_xor(c, input, output),
+            Backend::IsaLRsVand => gf256::mul_slice_xor(c as u8, input, output),
+        }
+    }
+
+    fn invert_matrix(self, matrix: &[Vec<u32>]) -> Option<Vec<Vec<u32>>> {
+        match self {
+            Backend::JerasureRsVand => gf2p32::invert_matrix(matrix),
+            Backend::IsaLRsVand => {
+                let matrix = matrix
+                    .iter()
+                    .map(|row| row.iter().map(|&x| x as u8).collect())
+                    .collect::<Vec<_>>();
+                gf256::invert_matrix(&matrix).map(|inverse| {
+                    inverse
+                        .into_iter()
+                        .map(|row| row.into_iter().map(u32::from).collect())
+                        .collect()
+                })
+            }
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    /// Parses the name of a variant (e.g., `"JerasureRsVand"`).
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "JerasureRsVand" => Ok(Backend::JerasureRsVand),
+            "IsaLRsVand" => Ok(Backend::IsaLRsVand),
+            _ => track_panic!(ErrorKind::InvalidInput, "Unknown backend: {:?}", s),
+        }
+    }
+}
+
+/// Checksum algorithms of fragment payloads.
+///
+/// Note that liberasurecode-1.5.0 records `Md5` in fragment headers but does not compute any digest for it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Checksum {
+    /// No checksum.
+    #[default]
+    None,
+
+    /// CRC-32.
+    Crc32,
+
+    /// MD5.
+    Md5,
+}
+impl Checksum {
+    /// Returns the identifier of the checksum algorithm stored in fragment headers.
+    pub fn id(self) -> u8 {
+        match self {
+            Checksum::None => 1,
+            Checksum::Crc32 => 2,
+            Checksum::Md5 => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Checksum::None),
+            2 => Some(Checksum::Crc32),
+            3 => Some(Checksum::Md5),
+            _ => None,
+        }
+    }
+}
+impl FromStr for Checksum {
+    type Err = Error;
+
+    /// Parses the name of a variant (e.g., `"Crc32"`).
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "None" => Ok(Checksum::None),
+            "Crc32" => Ok(Checksum::Crc32),
+            "Md5" => Ok(Checksum::Md5),
+            _ => track_panic!(ErrorKind::InvalidInput, "Unknown checksum: {:?}", s),
+        }
+    }
+}
+
+/// The fragment header of liberasurecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FragmentHeader {
+    /// The index of the fragment.
+    pub index: usize,
+
+    /// The size of the payload that follows the header.
+    pub payload_size: usize,
+
+    /// The size of the backend specific metadata.
+    pub backend_metadata_size: usize,
+
+    /// The size of the original data.
+    pub data_len: usize,
+
+    /// The identifier of the checksum algorithm (see [`Checksum::id`]).
+    ///
+    /// [`Checksum::id`]: ./enum.Checksum.html#method.id
+    pub checksum_type: u8,
+
+    /// The checksum of the payload.
+    pub checksum: [u32; 8],
+
+    /// Whether a checksum mismatch has been detected.
+    pub checksum_mismatch: bool,
+
+    /// The identifier of the backend (see [`Backend::id`]).
+    ///
+    /// [`Backend::id`]: ./enum.Backend.html#method.id
+    pub backend_id: u8,
+
+    /// The version of the backend.
+    pub backend_version: u32,
+
+    /// The version of liberasurecode that produced the fragment.
+    pub libec_version: u32,
+}
+impl FragmentHeader {
+    /// The size of a fragment header in bytes.
+    pub const SIZE: usize = 80;
+
+    /// The magic number of fragment headers.
+    pub const MAGIC: u32 = 0x0b0c_5ecc;
+
+    /// The version of liberasurecode written in the headers produced by this crate.
+    pub const LIBERASURECODE_VERSION: u32 = 0x01_05_00;
+
+    const METADATA_SIZE: usize = 59;
+
+    /// Parses the header of the given fragment.
+    ///
+    /// This validates the magic number and the metadata checksum of the header.
+    pub fn parse(fragment: &Fragment) -> Result<Self> {
+        track_assert!(
+            fragment.len() >= Self::SIZE,
+            ErrorKind::CorruptedFragments,
+            "Too short fragment: len={}",
+            fragment.len()
+        );
+        let magic = read_u32(fragment, 59);
+        track_assert_eq!(
+            magic,
+            Self::MAGIC,
+            ErrorKind::CorruptedFragments,
+            "Invalid fragment header"
+        );
+
+        let libec_version = read_u32(fragment, 63);
+        if libec_version >= version(1, 2, 0) {
+            // Accepts both the checksum of liberasurecode-1.5.0 and the standard one
+            // (later versions of liberasurecode use the latter).
+            let metadata = &fragment[..Self::METADATA_SIZE];
+            let metadata_checksum = read_u32(fragment, 67);
+            track_assert!(
+                metadata_checksum == crc32::crc32_liberasurecode(metadata)
+                    || metadata_checksum == crc32::crc32(metadata),
+                ErrorKind::CorruptedFragments,
+                "Metadata checksum mismatch"
+            );
+        }
+
+        let mut checksum = [0; 8];
+        for (i, c) in checksum.iter_mut().enumerate() {
+            *c = read_u32(fragment, 21 + i * 4);
+        }
+        let header = FragmentHeader {
+            index: read_u32(fragment, 0) as usize,
+            payload_size: read_u32(fragment, 4) as usize,
+            backend_metadata_size: read_u32(fragment, 8) as usize,
+            data_len: read_u64(fragment, 12) as usize,
+            checksum_type: fragment[20],
+            checksum,
+            checksum_mismatch: fragment[53] != 0,
+            backend_id: fragment[54],
+            backend_version: read_u32(fragment, 55),
+            libec_version,
+        };
+        track_assert_eq!(
+            fragment.len() - Self::SIZE,
+            header.payload_size,
+            ErrorKind::CorruptedFragments,
+            "Payload size mismatch: index={}",
+            header.index
+        );
+        Ok(header)
+    }
+
+    /// Writes the header to the first `FragmentHeader::SIZE` bytes of `buf`.
+    ///
+    /// The magic number and the metadata checksum are computed automatically.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is shorter than `FragmentHeader::SIZE`.
+    pub fn write_to(&self, buf: &mut [u8]) {
+        let buf = &mut buf[..Self::SIZE];
+        buf[0..4].copy_from_slice(&(self.index as u32).to_le_bytes());
+        buf[4..8].copy_from_slice(&(self.payload_size as u32).to_le_bytes());
+        buf[8..12].copy_from_slice(&(self.backend_metadata_size as u32).to_le_bytes());
+        buf[12..20].copy_from_slice(&(self.data_len as u64).to_le_bytes());
+        buf[20] = self.checksum_type;
+        for (i, c) in self.checksum.iter().enumerate() {
+            buf[21 + i * 4..][..4].copy_from_slice(&c.to_le_bytes());
+        }
+        buf[53] = self.checksum_mismatch as u8;
+        buf[54] = self.backend_id;
+        buf[55..59].copy_from_slice(&self.backend_version.to_le_bytes());
+        buf[59..63].copy_from_slice(&Self::MAGIC.to_le_bytes());
+        buf[63..67].copy_from_slice(&self.libec_version.to_le_bytes());
+        let metadata_checksum = crc32::crc32_liberasurecode(&buf[..Self::METADATA_SIZE]);
+        buf[67..71].copy_from_slice(&metadata_checksum.to_le_bytes());
+        for b in &mut buf[71..] {
+            *b = 0;
+        }
+    }
+
+    /// Returns the checksum algorithm of the payload.
+    ///
+    /// `None` is returned if the algorithm is unknown.
+    pub fn checksum_algorithm(&self) -> Option<Checksum> {
+        Checksum::from_id(self.checksum_type)
+    }
+}
+
+/// Checks that the given header was produced by the coder identified by `coder_id`.
+///
+/// `backend_id` and `checksum_id` are not checked if those are unknown.
+pub(crate) fn check_header(
+    header: &FragmentHeader,
+    coder_id: &str,
+    fragments: usize,
+    backend_id: Option<u8>,
+    checksum_id: Option<u8>,
+) -> Result<()> {
+    track_assert!(
+        header.index < fragments,
+        ErrorKind::CoderMismatch,
+        "Too large index: coder={}, expected_fragments={}, found_index={}",
+        coder_id,
+        fragments,
+        header.index
+    );
+    if let Some(backend_id) = backend_id {
+        track_assert_eq!(
+            header.backend_id,
+            backend_id,
+            ErrorKind::CoderMismatch,
+            "Backend mismatch: coder={}, expected_backend={}, found_backend={}",
+            coder_id,
+            backend_name(backend_id),
+            backend_name(header.backend_id)
+        );
+    }
+    if let Some(checksum_id) = checksum_id {
+        track_assert_eq!(
+            header.checksum_type,
+            checksum_id,
+            ErrorKind::CoderMismatch,
+            "Checksum mismatch: coder={}, expected_checksum={}, found_checksum={}",
+            coder_id,
+            checksum_name(checksum_id),
+            checksum_name(header.checksum_type)
+        );
+    }
+    Ok(())
+}
+
+/// Returns the size of the payload of each fragment that is made from data of `data_len` bytes.
+///
+/// Like liberasurecode, the data is padded to a multiple of `data_fragments * alignment` bytes.
+pub(crate) fn payload_size(data_len: usize, data_fragments: usize, alignment: usize) -> usize {
+    let alignment = data_fragments * alignment;
+    data_len.div_ceil(alignment) * alignment / data_fragments
+}
+
+/// Returns the metadata of the given fragment that has liberasurecode's fragment header.
+pub(crate) fn fragment_info(fragment: &Fragment) -> Result<FragmentInfo> {
+    let header = track!(FragmentHeader::parse(fragment))?;
+    let checksum = Some(header.checksum_type)
+        .filter(|&id| id != Checksum::None.id())
+        .map(checksum_name);
+    Ok(FragmentInfo {
+        index: Some(header.index),
+        data_len: Some(header.data_len),
+        payload_len: header.payload_size,
+        checksum,
+        backend: backend_name(header.backend_id),
+    })
+}
+
+fn coder_id(
+    backend: Backend,
+    checksum: Checksum,
+    data_fragments: NonZeroUsize,
+    parity_fragments: NonZeroUsize,
+) -> String {
+    format!(
+        "pure_liberasurecode:{:?}:{:?}:{}:{}",
+        backend, checksum, data_fragments, parity_fragments
+    )
+}
+
+/// Returns the name of the backend identified by `id` in liberasurecode.
+fn backend_name(id: u8) -> String {
+    match id {
+        0 => "null".to_owned(),
+        1 => "jerasure_rs_vand".to_owned(),
+        2 => "jerasure_rs_cauchy".to_owned(),
+        3 => "flat_xor_hd".to_owned(),
+        4 => "isa_l_rs_vand".to_owned(),
+        5 => "shss".to_owned(),
+        6 => "liberasurecode_rs_vand".to_owned(),
+        7 => "isa_l_rs_cauchy".to_owned(),
+        8 => "libphazr".to_owned(),
+        id => format!("unknown({})", id),
+    }
+}
+
+/// Returns the name of the checksum algorithm identified by `id` in liberasurecode.
+fn checksum_name(id: u8) -> String {
+    match id {
+        1 => "none".to_owned(),
+        2 => "crc32".to_owned(),
+        3 => "md5".to_owned(),
+        id => format!("unknown({})", id),
+    }
+}
+
+/// [`PureLibErasureCoder`] builder.
+///
+/// [`PureLibErasureCoder`]: ./struct.PureLibErasureCoder.html
+#[derive(Debug, Clone)]
+pub struct PureLibErasureCoderBuilder {
+    data_fragments: NonZeroUsize,
+    parity_fragments: NonZeroUsize,
+    backend: Backend,
+    checksum: Checksum,
+}
+impl PureLibErasureCoderBuilder {
+    /// Makes a new `PureLibErasureCoderBuilder` with the default settings.
+    pub fn new(data_fragments: NonZeroUsize, parity_fragments: NonZeroUsize) -> Self {
+        PureLibErasureCoderBuilder {
+            data_fragments,
+            parity_fragments,
+            backend: Backend::default(),
+            checksum: Checksum::default(),
+        }
+    }
+
+    /// Sets the type of the erasure coding backend used by the resulting instance.
+    ///
+    /// The default value is `Backend::JerasureRsVand`.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the checksum algorithm used by the resulting instance.
+    ///
+    /// The default value is `Checksum::None`.
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = checksum;
+        self
+    }
+}
+impl BuildCoder for PureLibErasureCoderBuilder {
+    type Coder = PureLibErasureCoder;
+
+    fn build_coder(&self) -> Result<Self::Coder> {
+        let k = self.data_fragments.get();
+        let m = self.parity_fragments.get();
+        let coding_matrix = match self.backend {
+            Backend::JerasureRsVand => jerasure_vandermonde_coding_matrix(k, m),
+            Backend::IsaLRsVand => {
+                track_assert!(
+                    k + m <= 256,
+                    ErrorKind::InvalidInput,
+                    "Too many fragments: data_fragments={}, parity_fragments={}",
+                    k,
+                    m
+                );
+                isa_l_vandermonde_coding_matrix(k, m)
+            }
+        };
+        Ok(PureLibErasureCoder {
+            data_fragments: self.data_fragments,
+            parity_fragments: self.parity_fragments,
+            backend: self.backend,
+            checksum: self.checksum,
+            coding_matrix,
+        })
+    }
+
+    fn coder_id(&self) -> String {
+        coder_id(
+            self.backend,
+            self.checksum,
+            self.data_fragments,
+            self.parity_fragments,
+        )
+    }
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for PureLibErasureCoderBuilder {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let repr = crate::serialize::LibErasureCodeRepr {
+            version: crate::serialize::VERSION,
+            data_fragments: self.data_fragments,
+            parity_fragments: self.parity_fragments,
+            backend: format!("{:?}", self.backend),
+            checksum: format!("{:?}", self.checksum),
+        };
+        serde::Serialize::serialize(&repr, serializer)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PureLibErasureCoderBuilder {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr: crate::serialize::LibErasureCodeRepr =
+            crate::serialize::deserialize(deserializer)?;
+        let backend = repr.backend.parse().map_err(crate::serialize::invalid)?;
+        let checksum = repr.checksum.parse().map_err(crate::serialize::invalid)?;
+        Ok(
+            PureLibErasureCoderBuilder::new(repr.data_fragments, repr.parity_fragments)
+                .backend(backend)
+                .checksum(checksum),
+        )
+    }
+}
+
+/// A pure-Rust [`ErasureCode`] implementation that is byte-compatible with [openstack/liberasurecode].
+///
+/// [`ErasureCode`]: ../trait.ErasureCode.html
+/// [openstack/liberasurecode]: https://github.com/openstack/liberasurecode
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::{ErasureCode, ErrorKind};
+/// use ecpool::pure_liberasurecode::PureLibErasureCoder;
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data_fragments = NonZeroUsize::new(4).ok_or("invalid input")?;
+/// let parity_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let mut coder = PureLibErasureCoder::new(data_fragments, parity_fragments)?;
+///
+/// // Encodes
+/// let data = vec![0, 1, 2, 3];
+/// let encoded = coder.encode(&data)?;
+/// let encoded = encoded.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+///
+/// // Decodes
+/// assert_eq!(Some(&data), coder.decode(&encoded[0..]).as_ref().ok());
+/// assert_eq!(Some(&data), coder.decode(&encoded[1..]).as_ref().ok());
+/// assert_eq!(Some(&data), coder.decode(&encoded[2..]).as_ref().ok());
+/// assert_eq!(Err(ErrorKind::InvalidInput), coder.decode(&encoded[3..]).map_err(|e| *e.kind()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PureLibErasureCoder {
+    data_fragments: NonZeroUsize,
+    parity_fragments: NonZeroUsize,
+    backend: Backend,
+    checksum: Checksum,
+    coding_matrix: Vec<Vec<u32>>,
+}
+impl PureLibErasureCoder {
+    /// Makes a new `PureLibErasureCoder` instance with the default settings.
+    ///
+    /// This is equivalent to `PureLibErasureCoderBuilder::new(data_fragments, parity_fragments).build_coder()`.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(data_fragments: NonZeroUsize, parity_fragments: NonZeroUsize) -> Result<Self> {
+        track!(PureLibErasureCoderBuilder::new(data_fragments, parity_fragments).build_coder())
+    }
+
+    /// Returns the backend of the instance.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Returns the checksum algorithm of the instance.
+    pub fn checksum(&self) -> Checksum {
+        self.checksum
+    }
+
+    fn coder_id(&self) -> String {
+        coder_id(
+            self.backend,
+            self.checksum,
+            self.data_fragments,
+            self.parity_fragments,
+        )
+    }
+
+    fn generator_row(&self, index: usize) -> Vec<u32> {
+        let k = self.data_fragments.get();
+        if index < k {
+            (0..k).map(|i| (i == index) as u32).collect()
+        } else {
+            self.coding_matrix[index - k].clone()
+        }
+    }
+
+    fn payload_size(&self, data_len: usize) -> usize {
+        payload_size(
+            data_len,
+            self.data_fragments.get(),
+            self.backend.word_size(),
+        )
+    }
+
+    fn write_header(&self, index: usize, data_len: usize, fragment: &mut [u8]) {
+        let (header, payload) = fragment.split_at_mut(FragmentHeader::SIZE);
+        let mut checksum = [0; 8];
+        if self.checksum == Checksum::Crc32 {
+            checksum[0] = crc32::crc32_liberasurecode(payload);
+        }
+        FragmentHeader {
+            index,
+            payload_size: payload.len(),
+            backend_metadata_size: 0,
+            data_len,
+            checksum_type: self.checksum.id(),
+            checksum,
+            checksum_mismatch: false,
+            backend_id: self.backend.id(),
+            backend_version: self.backend.version(),
+            libec_version: FragmentHeader::LIBERASURECODE_VERSION,
+        }
+        .write_to(header);
+    }
+
+    /// Parses the given fragments and arranges those payloads by their indices.
+    ///
+    /// Returns the size of the original data and the payloads.
+    ///
+    /// If an index is specified with a fragment, it is used instead of the one recorded in the header.
+    fn arrange<'a, I>(&self, fragments: I) -> Result<(usize, Vec<Option<&'a [u8]>>)>
+    where
+        I: IntoIterator<Item = (Option<usize>, &'a Fragment)>,
+    {
+        let mut data_len = None;
+        let mut payloads = vec![None; self.fragments().get()];
+        for (index, fragment) in fragments {
+            let mut header = track!(FragmentHeader::parse(fragment))?;
+            if let Some(index) = index {
+                track_assert!(
+                    index < payloads.len(),
+                    ErrorKind::InvalidInput,
+                    "Too large index: index={}, fragments={}",
+                    index,
+                    payloads.len()
+                );
+                header.index = index;
+            }
+            track!(check_header(
+                &header,
+                &self.coder_id(),
+                payloads.len(),
+                Some(self.backend.id()),
+                Some(self.checksum.id())
+            ))?;
+            let expected_data_len = *data_len.get_or_insert(header.data_len);
+            track_assert_eq!(
+                header.data_len,
+                expected_data_len,
+                ErrorKind::CorruptedFragments,
+                "Inconsistent data size: index={}",
+                header.index
+            );
+            track_assert_eq!(
+                header.payload_size,
+                self.payload_size(header.data_len),
+                ErrorKind::CoderMismatch,
+                "Unexpected payload size: coder={}, index={}, data_len={}",
+                self.coder_id(),
+                header.index,
+                header.data_len
+            );
+            if payloads[header.index].is_none() {
+                payloads[header.index] = Some(&fragment[FragmentHeader::SIZE..]);
+            }
+        }
+        let data_len = track_assert_some!(data_len, ErrorKind::InvalidInput, "No fragments");
+        Ok((data_len, payloads))
+    }
+
+    /// Recovers all of the data payloads.
+    ///
+    /// `payloads` may be the same sub-ranges of the payloads if those are aligned to the word size.
+    fn recover_data(&self, payloads: &[Option<&[u8]>]) -> Result<Vec<Vec<u8>>> {
+        let k = self.data_fragments.get();
+        let available = payloads
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_some())
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        track_assert!(
+            available.len() >= k,
+            ErrorKind::InvalidInput,
+            "fragments={}, data_fragments={}",
+            available.len(),
+            k
+        );
+
+        let mut data = payloads[..k]
+            .iter()
+            .map(|p| p.map(|p| p.to_vec()))
+            .collect::<Vec<_>>();
+        if data.iter().any(|d| d.is_none()) {
+            let chosen = &available[..k];
+            let matrix = chosen
+                .iter()
+                .map(|&i| self.generator_row(i))
+                .collect::<Vec<_>>();
+            let inverse = track_assert_some!(
+                self.backend.invert_matrix(&matrix),
+                ErrorKind::Other,
+                "Singular decoding matrix: chosen={:?}",
+                chosen
+            );
+            let payload_size = payloads[chosen[0]].map_or(0, |p| p.len());
+            for (i, d) in data.iter_mut().enumerate().filter(|(_, d)| d.is_none()) {
+                let mut payload = vec![0; payload_size];
+                for (&c, &j) in inverse[i].iter().zip(chosen.iter()) {
+                    let input = payloads[j].expect("Never fails");
+                    self.backend.mul_slice_xor(c, input, &mut payload);
+                }
+                *d = Some(payload);
+            }
+        }
+        Ok(data.into_iter().map(|d| d.expect("Never fails")).collect())
+    }
+
+    fn decode_payloads(&self, data_len: usize, payloads: &[Option<&[u8]>]) -> Result<Vec<u8>> {
+        let mut data = track!(self.recover_data(payloads))?.concat();
+        data.truncate(data_len);
+        Ok(data)
+    }
+
+    fn reconstruct_payloads(
+        &self,
+        index: usize,
+        data_len: usize,
+        payloads: &[Option<&[u8]>],
+    ) -> Result<Vec<u8>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        let data = track!(self.recover_data(payloads))?;
+        Ok(self.make_fragment(index, data_len, &data))
+    }
+
+    fn make_fragment(&self, index: usize, data_len: usize, data: &[Vec<u8>]) -> FragmentBuf {
+        let mut fragment = vec![0; FragmentHeader::SIZE + self.payload_size(data_len)];
+        for (&c, d) in self.generator_row(index).iter().zip(data.iter()) {
+            self.backend
+                .mul_slice_xor(c, d, &mut fragment[FragmentHeader::SIZE..]);
+        }
+        self.write_header(index, data_len, &mut fragment);
+        fragment
+    }
+}
+impl ErasureCode for PureLibErasureCoder {
+    fn data_fragments(&self) -> NonZeroUsize {
+        self.data_fragments
+    }
+
+    fn parity_fragments(&self) -> NonZeroUsize {
+        self.parity_fragments
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<FragmentBuf>> {
+        let fragment_size = FragmentHeader::SIZE + self.payload_size(data.len());
+        let mut fragments = vec![vec![0; fragment_size]; self.fragments().get()];
+        let mut buffers = fragments.iter_mut().map(|f| &mut f[..]).collect::<Vec<_>>();
+        track!(self.encode_into(data, &mut buffers))?;
+        Ok(fragments)
+    }
+
+    fn fragment_size(&self, data_len: usize) -> Option<usize> {
+        Some(FragmentHeader::SIZE + self.payload_size(data_len))
+    }
+
+    fn encode_into(&mut self, data: &[u8], buffers: &mut [&mut [u8]]) -> Result<()> {
+        let k = self.data_fragments.get();
+        let payload_size = self.payload_size(data.len());
+        track!(crate::check_buffers(
+            buffers,
+            self.fragments().get(),
+            Some(FragmentHeader::SIZE + payload_size)
+        ))?;
+        let (data_buffers, parity_buffers) = buffers.split_at_mut(k);
+        for (i, buffer) in data_buffers.iter_mut().enumerate() {
+            let start = std::cmp::min(i * payload_size, data.len());
+            let end = std::cmp::min(start + payload_size, data.len());
+            let payload = &mut buffer[FragmentHeader::SIZE..];
+            payload[..end - start].copy_from_slice(&data[start..end]);
+            payload[end - start..].iter_mut().for_each(|b| *b = 0);
+            self.write_header(i, data.len(), buffer);
+        }
+        for (i, buffer) in parity_buffers.iter_mut().enumerate() {
+            let payload = &mut buffer[FragmentHeader::SIZE..];
+            payload.iter_mut().for_each(|b| *b = 0);
+            for (&c, d) in self.generator_row(k + i).iter().zip(data_buffers.iter()) {
+                self.backend
+                    .mul_slice_xor(c, &d[FragmentHeader::SIZE..], payload);
+            }
+            self.write_header(k + i, data.len(), buffer);
+        }
+        Ok(())
+    }
+
+    /// Computes the parity fragments that correspond to the updated data fragment.
+    ///
+    /// The checksums in the headers of the resulting parity fragments are recomputed.
+    /// Note that the checksum in the header of `new_data` is not validated nor updated by this.
+    fn update_parity(
+        &mut self,
+        data_index: usize,
+        old_data: &Fragment,
+        new_data: &Fragment,
+        parities: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        let k = self.data_fragments.get();
+        track_assert!(
+            data_index < k,
+            ErrorKind::InvalidInput,
+            "Not a data fragment: data_index={}, data_fragments={}",
+            data_index,
+            k
+        );
+        track_assert_eq!(
+            parities.len(),
+            self.parity_fragments.get(),
+            ErrorKind::InvalidInput
+        );
+        let fragments = [old_data, new_data]
+            .iter()
+            .chain(parities.iter())
+            .map(|f| (None, *f))
+            .collect::<Vec<_>>();
+        let (data_len, _) = track!(self.arrange(fragments))?;
+        for (i, fragment) in [old_data, new_data]
+            .iter()
+            .chain(parities.iter())
+            .enumerate()
+        {
+            let expected = if i < 2 { data_index } else { k + i - 2 };
+            track_assert_eq!(
+                track!(FragmentHeader::parse(fragment))?.index,
+                expected,
+                ErrorKind::InvalidInput,
+                "Unexpected fragment index"
+            );
+        }
+
+        let delta = old_data[FragmentHeader::SIZE..]
+            .iter()
+            .zip(new_data[FragmentHeader::SIZE..].iter())
+            .map(|(a, b)| a ^ b)
+            .collect::<Vec<_>>();
+        Ok(parities
+            .iter()
+            .enumerate()
+            .map(|(i, parity)| {
+                let mut parity = parity.to_vec();
+                let c = self.generator_row(k + i)[data_index];
+                self.backend
+                    .mul_slice_xor(c, &delta, &mut parity[FragmentHeader::SIZE..]);
+                self.write_header(k + i, data_len, &mut parity);
+                parity
+            })
+            .collect())
+    }
+
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        track!(fragment_info(fragment))
+    }
+
+    /// Verifies the given fragments.
+    ///
+    /// In addition to the consistency of the payloads,
+    /// this checks the backend and the payload checksum recorded in the header of each fragment.
+    fn verify(&mut self, fragments: &[&Fragment]) -> VerifyReport {
+        let backend_id = self.backend.id();
+        verify::verify(self, fragments, FragmentHeader::SIZE, |fragment| {
+            let header = FragmentHeader::parse(fragment).ok()?;
+            let payload = &fragment[FragmentHeader::SIZE..];
+            let checksum_ok = match header.checksum_algorithm() {
+                Some(Checksum::Crc32) => {
+                    header.checksum[0] == crc32::crc32_liberasurecode(payload)
+                        || header.checksum[0] == crc32::crc32(payload)
+                }
+                _ => true,
+            };
+            Some(FragmentMeta {
+                index: header.index,
+                data_len: header.data_len,
+                intact: header.backend_id == backend_id && !header.checksum_mismatch && checksum_ok,
+            })
+        })
+    }
+
+    fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let (data_len, payloads) = track!(self.arrange(fragments.iter().map(|f| (None, *f))))?;
+        track!(self.decode_payloads(data_len, &payloads))
+    }
+
+    fn reconstruct_many(
+        &mut self,
+        indices: &[usize],
+        fragments: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        for &index in indices {
+            track_assert!(
+                index < self.fragments().get(),
+                ErrorKind::Other,
+                "Too large index: index={}, fragments={}",
+                index,
+                self.fragments()
+            );
+        }
+        let (data_len, payloads) = track!(self.arrange(fragments.iter().map(|f| (None, *f))))?;
+        let data = track!(self.recover_data(&payloads))?;
+        Ok(indices
+            .iter()
+            .map(|&i| self.make_fragment(i, data_len, &data))
+            .collect())
+    }
+
+    fn decode_indexed(&mut self, fragments: &[(usize, &Fragment)]) -> Result<Vec<u8>> {
+        let fragments = fragments.iter().map(|&(i, f)| (Some(i), f));
+        let (data_len, payloads) = track!(self.arrange(fragments))?;
+        track!(self.decode_payloads(data_len, &payloads))
+    }
+
+    fn decode_range(&mut self, range: Range<usize>, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let (data_len, payloads) = track!(self.arrange(fragments.iter().map(|f| (None, *f))))?;
+        track!(plan::check_data_range(&range, data_len))?;
+        let word_size = self.backend.word_size();
+        let mut data = Vec::with_capacity(range.len());
+        for (i, r) in plan::split_range(range, self.payload_size(data_len)) {
+            if let Some(payload) = payloads[i] {
+                data.extend_from_slice(&payload[r]);
+            } else {
+                let start = r.start / word_size * word_size;
+                let end = r.end.div_ceil(word_size) * word_size;
+                let sliced = payloads
+                    .iter()
+                    .map(|p| p.map(|p| &p[start..end]))
+                    .collect::<Vec<_>>();
+                let recovered = track!(self.recover_data(&sliced))?;
+                data.extend_from_slice(&recovered[i][r.start - start..r.end - start]);
+            }
+        }
+        Ok(data)
+    }
+
+    fn fragment_ranges(&self, range: Range<usize>, data_len: usize) -> Option<Vec<FragmentRange>> {
+        let payload_len = self.payload_size(data_len);
+        plan::data_fragment_ranges(range, data_len, payload_len, FragmentHeader::SIZE)
+    }
+
+    fn reconstruct(&mut self, index: usize, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let (data_len, payloads) = track!(self.arrange(fragments.iter().map(|f| (None, *f))))?;
+        track!(self.reconstruct_payloads(index, data_len, &payloads))
+    }
+
+    fn reconstruct_indexed(
+        &mut self,
+        index: usize,
+        fragments: &[(usize, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        let fragments = fragments.iter().map(|&(i, f)| (Some(i), f));
+        let (data_len, payloads) = track!(self.arrange(fragments))?;
+        track!(self.reconstruct_payloads(index, data_len, &payloads))
+    }
+}
+
+const fn version(major: u32, minor: u32, revision: u32) -> u32 {
+    (major << 16) | (minor << 8) | revision
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Makes the same coding matrix as jerasure's `reed_sol_vandermonde_coding_matrix(k, m, 32)`.
+fn jerasure_vandermonde_coding_matrix(k: usize, m: usize) -> Vec<Vec<u32>> {
+    let rows = k + m;
+    let cols = k;
+
+    // reed_sol_extended_vandermonde_matrix()
+    let mut dist = vec![vec![0u32; cols]; rows];
+    dist[0][0] = 1;
+    dist[rows - 1][cols - 1] = 1;
+    for (i, row) in dist.iter_mut().enumerate().take(rows - 1).skip(1) {
+        let mut x = 1;
+        for v in row.iter_mut() {
+            *v = x;
+            x = gf2p32::mul(x, i as u32);
+        }
+    }
+
+    // reed_sol_big_vandermonde_distribution_matrix()
+    for i in 1..cols {
+        let j = (i..rows)
+            .find(|&j| dist[j][i] != 0)
+            .expect("Never fails when `w = 32`");
+        dist.swap(i, j);
+
+        if dist[i][i] != 1 {
+            let t = gf2p32::div(1, dist[i][i]);
+            for row in dist.iter_mut() {
+                row[i] = gf2p32::mul(t, row[i]);
+            }
+        }
+        for j in (0..cols).filter(|&j| j != i) {
+            let t = dist[i][j];
+            if t == 0 {
+                continue;
+            }
+            for row in dist.iter_mut() {
+                row[j] ^= gf2p32::mul(t, row[i]);
+            }
+        }
+    }
+    for j in 0..cols {
+        let t = dist[cols][j];
+        if t != 1 {
+            let t = gf2p32::div(1, t);
+            for row in dist.iter_mut().skip(cols) {
+                row[j] = gf2p32::mul(t, row[j]);
+            }
+        }
+    }
+    for row in dist.iter_mut().skip(cols + 1) {
+        let t = row[0];
+        if t != 1 {
+            let t = gf2p32::div(1, t);
+            for v in row.iter_mut() {
+                *v = gf2p32::mul(*v, t);
+            }
+        }
+    }
+
+    dist.split_off(cols)
+}
+
+/// Makes the same coding matrix as ISA-L's `gf_gen_rs_matrix(a, k + m, k)`.
+fn isa_l_vandermonde_coding_matrix(k: usize, m: usize) -> Vec<Vec<u32>> {
+    let mut gen = 1;
+    (0..m)
+        .map(|_| {
+            let mut p = 1;
+            let row = (0..k)
+                .map(|_| {
+                    let v = u32::from(p);
+                    p = gf256::mul(p, gen);
+                    v
+                })
+                .collect();
+            gen = gf256::mul(gen, 2);
+            row
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::MainError;
+
+    use super::*;
+    use crate::test_util::{non_zero, test_data};
+    use crate::{ErasureCode, ErrorKind};
+
+    #[test]
+    fn it_works() -> Result<(), MainError> {
+        let mut coder = track!(PureLibErasureCoder::new(non_zero(4)?, non_zero(2)?))?;
+        let data = vec![0, 1, 2, 3];
+        let encoded = track!(coder.encode(&data))?;
+        let encoded = encoded.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+
+        assert_eq!(Some(&data), coder.decode(&encoded[0..]).as_ref().ok());
+        assert_eq!(Some(&data), coder.decode(&encoded[1..]).as_ref().ok());
+        assert_eq!(Some(&data), coder.decode(&encoded[2..]).as_ref().ok());
+        assert_eq!(
+            Err(ErrorKind::InvalidInput),
+            coder.decode(&encoded[3..]).map_err(|e| *e.kind())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reconstruct_works_for_all_fragments() -> Result<(), MainError> {
+        let (k, m) = (4, 3);
+        let data = test_data(1000);
+        for &backend in &[Backend::JerasureRsVand, Backend::IsaLRsVand] {
+            let mut coder = track!(PureLibErasureCoderBuilder::new(non_zero(k)?, non_zero(m)?)
+                .backend(backend)
+                .checksum(Checksum::Crc32)
+                .build_coder())?;
+            let encoded = track!(coder.encode(&data))?;
+            for alive in 0usize..1 << (k + m) {
+                if alive.count_ones() as usize != k {
+                    continue;
+                }
+                let fragments = (0..k + m)
+                    .filter(|i| alive & (1 << i) != 0)
+                    .map(|i| &encoded[i][..])
+                    .collect::<Vec<_>>();
+                assert_eq!(track!(coder.decode(&fragments))?, data);
+                assert_eq!(
+                    track!(coder.decode_range(250..503, &fragments))?,
+                    &data[250..503]
+                );
+                for index in (0..k + m).filter(|i| alive & (1 << i) == 0) {
+                    assert_eq!(
+                        track!(coder.reconstruct(index, &fragments))?,
+                        encoded[index]
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn jerasure_coding_matrix_is_normalized() {
+        // As the result of `reed_sol_big_vandermonde_distribution_matrix()`,
+        // the first row and the first column of the coding matrix consist of ones.
+        let matrix = jerasure_vandermonde_coding_matrix(6, 3);
+        assert!(matrix[0].iter().all(|&v| v == 1));
+        assert!(matrix.iter().all(|row| row[0] == 1));
+    }
+
+    #[cfg(all(unix, feature = "liberasurecode"))]
+    #[test]
+    fn compatible_with_libec() -> Result<(), MainError> {
+        use crate::liberasurecode::{self, LibErasureCoderBuilder};
+
+        let data = (0..0xc0de).map(|i| (i * 31 + 7) as u8).collect::<Vec<_>>();
+        for &backend in &[Backend::JerasureRsVand, Backend::IsaLRsVand] {
+            for &checksum in &[Checksum::None, Checksum::Crc32] {
+                let libec_checksum = match checksum {
+                    Checksum::None => liberasurecode::Checksum::None,
+                    _ => liberasurecode::Checksum::Crc32,
+                };
+
+                // The `liberasurecode` crate only provides the jerasure backends, so only the layouts
+                // of the fragments of the other backends are compared with the ones of `JerasureRsVand`
+                let mut libec = track!(LibErasureCoderBuilder::new(non_zero(6)?, non_zero(3)?)
+                    .backend(liberasurecode::Backend::JerasureRsVand)
+                    .checksum(libec_checksum)
+                    .build_coder())?;
+                let mut pure = track!(PureLibErasureCoderBuilder::new(non_zero(6)?, non_zero(3)?)
+                    .backend(backend)
+                    .checksum(checksum)
+                    .build_coder())?;
+
+                let expected = track!(libec.encode(&data))?;
+                let actual = track!(pure.encode(&data))?;
+                assert_eq!(actual.len(), expected.len());
+                for (a, e) in actual.iter().zip(expected.iter()) {
+                    let a_header = track!(FragmentHeader::parse(a))?;
+                    let e_header = track!(FragmentHeader::parse(e))?;
+                    assert_eq!(a_header.backend_id, backend.id());
+                    assert_eq!(
+                        (a_header.index, a_header.data_len, a_header.checksum_type),
+                        (e_header.index, e_header.data_len, e_header.checksum_type)
+                    );
+                }
+                let payloads = actual[..6]
+                    .iter()
+                    .flat_map(|f| f[FragmentHeader::SIZE..].iter().cloned())
+                    .collect::<Vec<_>>();
+                assert_eq!(payloads[..data.len()], data[..]);
+
+                let fragments = actual[3..].iter().map(|f| &f[..]).collect::<Vec<_>>();
+                assert_eq!(track!(pure.decode(&fragments))?, data);
+                assert_eq!(track!(pure.reconstruct(0, &fragments))?, actual[0]);
+                if backend != Backend::JerasureRsVand {
+                    continue;
+                }
+
+                assert_eq!(actual, expected);
+                let fragments = expected[3..].iter().map(|f| &f[..]).collect::<Vec<_>>();
+                assert_eq!(track!(pure.decode(&fragments))?, data);
+                assert_eq!(track!(pure.reconstruct(0, &fragments))?, expected[0]);
+
+                let fragments = actual[2..8].iter().map(|f| &f[..]).collect::<Vec<_>>();
+                assert_eq!(track!(libec.decode(&fragments))?, data);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn update_parity_recomputes_checksums() -> Result<(), MainError> {
+        let (k, m) = (4, 3);
+        let mut data = test_data(1000);
+        for &backend in &[Backend::JerasureRsVand, Backend::IsaLRsVand] {
+            let mut coder = track!(PureLibErasureCoderBuilder::new(non_zero(k)?, non_zero(m)?)
+                .backend(backend)
+                .checksum(Checksum::Crc32)
+                .build_coder())?;
+            let old = track!(coder.encode(&data))?;
+            data[0] ^= 0xFF;
+            let new = track!(coder.encode(&data))?;
+
+            let parities = old[k..].iter().map(|f| &f[..]).collect::<Vec<_>>();
+            assert_eq!(
+                track!(coder.update_parity(0, &old[0], &new[0], &parities))?,
+                new[k..].to_vec()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn verify_detects_checksum_mismatch() -> Result<(), MainError> {
+        let mut coder = track!(PureLibErasureCoderBuilder::new(non_zero(4)?, non_zero(2)?)
+            .checksum(Checksum::Crc32)
+            .build_coder())?;
+        let mut encoded = track!(coder.encode(&test_data(1000)))?;
+        encoded[1][FragmentHeader::SIZE] ^= 1;
+
+        // Only four fragments are intact, so their consistency cannot be checked
+        let fragments = encoded[1..].iter().map(|f| &f[..]).collect::<Vec<_>>();
+        let report = coder.verify(&fragments);
+        assert_eq!(report.missing, vec![0]);
+        assert_eq!(report.corrupt, vec![1]);
+        assert_eq!(report.unverified, vec![2, 3, 4, 5]);
+        Ok(())
+    }
+
+    #[test]
+    fn fragment_info_works() -> Result<(), MainError> {
+        let mut coder = track!(PureLibErasureCoderBuilder::new(non_zero(4)?, non_zero(2)?)
+            .backend(Backend::IsaLRsVand)
+            .checksum(Checksum::Crc32)
+            .build_coder())?;
+        let encoded = track!(coder.encode(&[0; 1000]))?;
+        assert_eq!(
+            track!(coder.fragment_info(&encoded[5]))?,
+            FragmentInfo {
+                index: Some(5),
+                data_len: Some(1000),
+                payload_len: 250,
+                checksum: Some("crc32".to_owned()),
+                backend: "isa_l_rs_vand".to_owned(),
+            }
+        );
+        assert_eq!(
+            coder
+                .fragment_info(&encoded[5][..10])
+                .map_err(|e| *e.kind()),
+            Err(ErrorKind::CorruptedFragments)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn coder_mismatch_is_detected() -> Result<(), MainError> {
+        let mut coder = track!(PureLibErasureCoder::new(non_zero(6)?, non_zero(3)?))?;
+        let encoded = track!(coder.encode(&test_data(1000)))?;
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+
+        let mut coders = vec![
+            track!(PureLibErasureCoder::new(non_zero(4)?, non_zero(2)?))?,
+            track!(PureLibErasureCoderBuilder::new(non_zero(6)?, non_zero(3)?)
+                .backend(Backend::IsaLRsVand)
+                .build_coder())?,
+            track!(PureLibErasureCoderBuilder::new(non_zero(6)?, non_zero(3)?)
+                .checksum(Checksum::Crc32)
+                .build_coder())?,
+        ];
+        for coder in &mut coders {
+            assert_eq!(
+                coder.decode(&fragments[..6]).map_err(|e| *e.kind()),
+                Err(ErrorKind::CoderMismatch)
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,540 @@
+//! An [`ErasureCode`] implementation of systematic Reed-Solomon coding written in pure Rust.
+//!
+//! [`ErasureCode`]: ../trait.ErasureCode.html
+use std::num::NonZeroUsize;
+use std::ops::Range;
+
+use crate::gf256;
+use crate::header::{self, FragmentHeader, Payloads};
+use crate::plan::{self, FragmentRange};
+use crate::verify;
+use crate::{
+    BuildCoder, ErasureCode, ErrorKind, Fragment, FragmentBuf, FragmentInfo, Result, VerifyReport,
+};
+
+/// An [`ErasureCode`] implementation of systematic Reed-Solomon coding over GF(2^8).
+///
+/// The parity fragments are computed by using a Cauchy matrix,
+/// so any `N` fragments out of `N + M` are sufficient to decode the original data
+/// (where `N = self.data_fragments()` and `M = self.parity_fragments()`).
+///
+/// Unlike [`LibErasureCoder`], this does not depend on any native libraries.
+/// Note that the fragments produced by this are not compatible with the ones of [`LibErasureCoder`].
+///
+/// [`ErasureCode`]: ../trait.ErasureCode.html
+/// [`LibErasureCoder`]: ../liberasurecode/struct.LibErasureCoder.html
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::{ErasureCode, ErrorKind};
+/// use ecpool::reedsolomon::ReedSolomonCoder;
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data_fragments = NonZeroUsize::new(4).ok_or("invalid input")?;
+/// let parity_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let mut coder = ReedSolomonCoder::new(data_fragments, parity_fragments)?;
+///
+/// // Encodes
+/// let data = vec![0, 1, 2, 3];
+/// let encoded = coder.encode(&data)?;
+/// let encoded = encoded.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+///
+/// // Decodes
+/// assert_eq!(Some(&data), coder.decode(&encoded[0..]).as_ref().ok());
+/// assert_eq!(Some(&data), coder.decode(&encoded[1..]).as_ref().ok());
+/// assert_eq!(Some(&data), coder.decode(&encoded[2..]).as_ref().ok());
+/// assert_eq!(Err(ErrorKind::InvalidInput), coder.decode(&encoded[3..]).map_err(|e| *e.kind()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReedSolomonCoder {
+    data_fragments: NonZeroUsize,
+    parity_fragments: NonZeroUsize,
+    parity_matrix: Vec<Vec<u8>>,
+}
+impl ReedSolomonCoder {
+    /// The maximum number of total fragments (i.e., `data_fragments + parity_fragments`).
+    pub const MAX_FRAGMENTS: usize = 256;
+
+    /// Makes a new `ReedSolomonCoder` instance.
+    ///
+    /// If `data_fragments + parity_fragments` exceeds `MAX_FRAGMENTS`,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn new(data_fragments: NonZeroUsize, parity_fragments: NonZeroUsize) -> Result<Self> {
+        track_assert!(
+            data_fragments.get() + parity_fragments.get() <= Self::MAX_FRAGMENTS,
+            ErrorKind::InvalidInput,
+            "Too many fragments: data_fragments={}, parity_fragments={}",
+            data_fragments,
+            parity_fragments
+        );
+        let parity_matrix = gf256::cauchy_matrix(parity_fragments.get(), data_fragments.get());
+        Ok(ReedSolomonCoder {
+            data_fragments,
+            parity_fragments,
+            parity_matrix,
+        })
+    }
+
+    /// Returns the coefficients of the generator matrix row for the fragment at `index`.
+    fn generator_row(&self, index: usize) -> Vec<u8> {
+        let k = self.data_fragments.get();
+        if index < k {
+            (0..k).map(|i| (i == index) as u8).collect()
+        } else {
+            self.parity_matrix[index - k].clone()
+        }
+    }
+
+    /// Recovers all of the data payloads from the given fragments.
+    fn recover_data(&self, payloads: &Payloads) -> Result<Vec<Vec<u8>>> {
+        let k = self.data_fragments.get();
+        track_assert!(
+            payloads.available() >= k,
+            ErrorKind::InvalidInput,
+            "fragments={}, data_fragments={}",
+            payloads.available(),
+            k
+        );
+
+        let mut data = payloads.slots[..k]
+            .iter()
+            .map(|s| s.map(|p| p.to_vec()))
+            .collect::<Vec<_>>();
+        if data.iter().all(|d| d.is_some()) {
+            return Ok(data.into_iter().map(|d| d.expect("Never fails")).collect());
+        }
+
+        // Prefers data fragments, since their generator rows are trivial.
+        let chosen = payloads
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.is_some())
+            .map(|(i, _)| i)
+            .take(k)
+            .collect::<Vec<_>>();
+        let matrix = chosen
+            .iter()
+            .map(|&i| self.generator_row(i))
+            .collect::<Vec<_>>();
+        let inverse = track_assert_some!(
+            gf256::invert_matrix(&matrix),
+            ErrorKind::Other,
+            "Singular decoding matrix: chosen={:?}",
+            chosen
+        );
+        for (i, d) in data.iter_mut().enumerate().filter(|(_, d)| d.is_none()) {
+            let mut payload = vec![0; payloads.payload_len];
+            for (&c, &j) in inverse[i].iter().zip(chosen.iter()) {
+                gf256::mul_slice_xor(c, payloads.slots[j].expect("Never fails"), &mut payload);
+            }
+            *d = Some(payload);
+        }
+        Ok(data.into_iter().map(|d| d.expect("Never fails")).collect())
+    }
+
+    /// Checks that the given payloads were produced with the same number of data fragments as this coder.
+    fn check_payloads(&self, payloads: &Payloads) -> Result<()> {
+        let expected = payloads.data_len.div_ceil(self.data_fragments.get());
+        track!(payloads.check_payload_len(&self.coder_id(), expected))
+    }
+
+    fn decode_payloads(&self, payloads: &Payloads) -> Result<Vec<u8>> {
+        track!(self.check_payloads(payloads))?;
+        let data_payloads = track!(self.recover_data(payloads))?;
+        let mut data = data_payloads.concat();
+        track_assert!(
+            payloads.data_len <= data.len(),
+            ErrorKind::CorruptedFragments,
+            "Too large data size: data_len={}, decoded={}",
+            payloads.data_len,
+            data.len()
+        );
+        data.truncate(payloads.data_len);
+        Ok(data)
+    }
+
+    fn reconstruct_payloads(&self, index: usize, payloads: &Payloads) -> Result<Vec<u8>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        track!(self.check_payloads(payloads))?;
+        let data = track!(self.recover_data(payloads))?;
+        Ok(self.make_fragment(index, payloads.data_len, &data))
+    }
+
+    fn make_fragment(&self, index: usize, data_len: usize, data: &[Vec<u8>]) -> FragmentBuf {
+        let payload_len = data.first().map_or(0, |d| d.len());
+        let mut fragment = vec![0; FragmentHeader::SIZE + payload_len];
+        FragmentHeader::new(index, data_len).write_to(&mut fragment);
+        let payload = &mut fragment[FragmentHeader::SIZE..];
+        for (&c, d) in self.generator_row(index).iter().zip(data.iter()) {
+            gf256::mul_slice_xor(c, d, payload);
+        }
+        fragment
+    }
+}
+impl ErasureCode for ReedSolomonCoder {
+    fn data_fragments(&self) -> NonZeroUsize {
+        self.data_fragments
+    }
+
+    fn parity_fragments(&self) -> NonZeroUsize {
+        self.parity_fragments
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<FragmentBuf>> {
+        let fragment_size = FragmentHeader::SIZE + data.len().div_ceil(self.data_fragments.get());
+        let mut fragments = vec![vec![0; fragment_size]; self.fragments().get()];
+        let mut buffers = fragments.iter_mut().map(|f| &mut f[..]).collect::<Vec<_>>();
+        track!(self.encode_into(data, &mut buffers))?;
+        Ok(fragments)
+    }
+
+    fn fragment_size(&self, data_len: usize) -> Option<usize> {
+        Some(FragmentHeader::SIZE + data_len.div_ceil(self.data_fragments.get()))
+    }
+
+    fn encode_into(&mut self, data: &[u8], buffers: &mut [&mut [u8]]) -> Result<()> {
+        let k = self.data_fragments.get();
+        let payload_len = data.len().div_ceil(k);
+        track!(crate::check_buffers(
+            buffers,
+            self.fragments().get(),
+            Some(FragmentHeader::SIZE + payload_len)
+        ))?;
+        let (data_buffers, parity_buffers) = buffers.split_at_mut(k);
+        header::write_data_fragments(data, payload_len, data_buffers);
+        for (i, (row, buffer)) in self
+            .parity_matrix
+            .iter()
+            .zip(parity_buffers.iter_mut())
+            .enumerate()
+        {
+            FragmentHeader::new(k + i, data.len()).write_to(buffer);
+            let payload = &mut buffer[FragmentHeader::SIZE..];
+            payload.iter_mut().for_each(|b| *b = 0);
+            for (&c, d) in row.iter().zip(data_buffers.iter()) {
+                gf256::mul_slice_xor(c, &d[FragmentHeader::SIZE..], payload);
+            }
+        }
+        Ok(())
+    }
+
+    fn update_parity(
+        &mut self,
+        data_index: usize,
+        old_data: &Fragment,
+        new_data: &Fragment,
+        parities: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        track_assert_eq!(
+            parities.len(),
+            self.parity_fragments.get(),
+            ErrorKind::InvalidInput
+        );
+        let delta = track!(header::parity_delta(
+            data_index,
+            old_data,
+            new_data,
+            parities,
+            self.data_fragments.get()
+        ))?;
+        Ok(parities
+            .iter()
+            .zip(self.parity_matrix.iter())
+            .map(|(parity, row)| {
+                let mut parity = parity.to_vec();
+                gf256::mul_slice_xor(row[data_index], &delta, &mut parity[FragmentHeader::SIZE..]);
+                parity
+            })
+            .collect())
+    }
+
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        track!(header::fragment_info(fragment, "reedsolomon"))
+    }
+
+    fn verify(&mut self, fragments: &[&Fragment]) -> VerifyReport {
+        verify::verify(self, fragments, FragmentHeader::SIZE, header::fragment_meta)
+    }
+
+    fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.decode_payloads(&payloads))
+    }
+
+    fn reconstruct_many(
+        &mut self,
+        indices: &[usize],
+        fragments: &[&Fragment],
+    ) -> Result<Vec<FragmentBuf>> {
+        for &index in indices {
+            track_assert!(
+                index < self.fragments().get(),
+                ErrorKind::Other,
+                "Too large index: index={}, fragments={}",
+                index,
+                self.fragments()
+            );
+        }
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.check_payloads(&payloads))?;
+        let data = track!(self.recover_data(&payloads))?;
+        Ok(indices
+            .iter()
+            .map(|&i| self.make_fragment(i, payloads.data_len, &data))
+            .collect())
+    }
+
+    fn decode_indexed(&mut self, fragments: &[(usize, &Fragment)]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_indexed_fragments(
+            fragments,
+            self.fragments().get()
+        ))?;
+        track!(self.decode_payloads(&payloads))
+    }
+
+    fn decode_range(&mut self, range: Range<usize>, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.check_payloads(&payloads))?;
+        track!(plan::check_data_range(&range, payloads.data_len))?;
+        let mut data = Vec::with_capacity(range.len());
+        for (i, r) in plan::split_range(range, payloads.payload_len) {
+            if let Some(payload) = payloads.slots[i] {
+                data.extend_from_slice(&payload[r]);
+            } else {
+                let mut recovered = track!(self.recover_data(&payloads.slice(r)))?;
+                data.append(&mut recovered[i]);
+            }
+        }
+        Ok(data)
+    }
+
+    fn fragment_ranges(&self, range: Range<usize>, data_len: usize) -> Option<Vec<FragmentRange>> {
+        let payload_len = data_len.div_ceil(self.data_fragments.get());
+        plan::data_fragment_ranges(range, data_len, payload_len, FragmentHeader::SIZE)
+    }
+
+    fn reconstruct(&mut self, index: usize, fragments: &[&Fragment]) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_fragments(fragments, self.fragments().get()))?;
+        track!(self.reconstruct_payloads(index, &payloads))
+    }
+
+    fn reconstruct_indexed(
+        &mut self,
+        index: usize,
+        fragments: &[(usize, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        let payloads = track!(Payloads::from_indexed_fragments(
+            fragments,
+            self.fragments().get()
+        ))?;
+        track!(self.reconstruct_payloads(index, &payloads))
+    }
+}
+impl BuildCoder for ReedSolomonCoder {
+    type Coder = Self;
+
+    fn build_coder(&self) -> Result<Self::Coder> {
+        Ok(self.clone())
+    }
+
+    fn coder_id(&self) -> String {
+        format!(
+            "reedsolomon:{}:{}",
+            self.data_fragments, self.parity_fragments
+        )
+    }
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for ReedSolomonCoder {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let repr = crate::serialize::FragmentsRepr::new(self.data_fragments, self.parity_fragments);
+        serde::Serialize::serialize(&repr, serializer)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ReedSolomonCoder {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr: crate::serialize::FragmentsRepr = crate::serialize::deserialize(deserializer)?;
+        ReedSolomonCoder::new(repr.data_fragments, repr.parity_fragments)
+            .map_err(crate::serialize::invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::{Failed, MainError};
+
+    use super::*;
+    use crate::test_util::{non_zero, test_data};
+    use crate::{ErasureCode, ErrorKind};
+
+    #[test]
+    fn it_works() -> Result<(), MainError> {
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?;
+        let data = vec![0, 1, 2, 3];
+        let encoded = track!(coder.encode(&data))?;
+        let encoded = encoded.iter().map(|f| f.as_ref()).collect::<Vec<_>>();
+
+        assert_eq!(Some(&data), coder.decode(&encoded[0..]).as_ref().ok());
+        assert_eq!(Some(&data), coder.decode(&encoded[1..]).as_ref().ok());
+        assert_eq!(Some(&data), coder.decode(&encoded[2..]).as_ref().ok());
+        assert_eq!(
+            Err(ErrorKind::InvalidInput),
+            coder.decode(&encoded[3..]).map_err(|e| *e.kind())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reconstruct_works_for_all_fragments() -> Result<(), MainError> {
+        let (k, m) = (4, 3);
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(k)?, non_zero(m)?))?;
+        let data = test_data(1000);
+        let encoded = track!(coder.encode(&data))?;
+
+        // Exhaustively checks all patterns that exactly `k` fragments are alive.
+        for alive in 0usize..1 << (k + m) {
+            if alive.count_ones() as usize != k {
+                continue;
+            }
+            let fragments = (0..k + m)
+                .filter(|i| alive & (1 << i) != 0)
+                .map(|i| &encoded[i][..])
+                .collect::<Vec<_>>();
+            assert_eq!(track!(coder.decode(&fragments))?, data);
+            for index in (0..k + m).filter(|i| alive & (1 << i) == 0) {
+                assert_eq!(
+                    track!(coder.reconstruct(index, &fragments))?,
+                    encoded[index]
+                );
+            }
+
+            let lost = (0..k + m)
+                .filter(|i| alive & (1 << i) == 0)
+                .collect::<Vec<_>>();
+            let expected = lost.iter().map(|&i| encoded[i].clone()).collect::<Vec<_>>();
+            assert_eq!(track!(coder.reconstruct_many(&lost, &fragments))?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn indexed_apis_trust_given_indices() -> Result<(), MainError> {
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?;
+        let data = (0..100).collect::<Vec<u8>>();
+        let mut encoded = track!(coder.encode(&data))?;
+
+        // Overwrites the indices recorded in the headers
+        for fragment in &mut encoded {
+            fragment[0..4].copy_from_slice(&[0; 4]);
+        }
+        let indexed = encoded
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (i, &f[..]))
+            .collect::<Vec<_>>();
+        assert_eq!(track!(coder.decode_indexed(&indexed[2..]))?, data);
+        assert_eq!(
+            track!(coder.reconstruct_indexed(1, &indexed[2..]))?,
+            track!(coder.encode(&data))?.swap_remove(1)
+        );
+        assert_eq!(
+            coder
+                .decode_indexed(&[(6, &encoded[0][..])])
+                .map_err(|e| *e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decode_range_works() -> Result<(), MainError> {
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?;
+        let data = test_data(103);
+        let encoded = track!(coder.encode(&data))?;
+        let encoded = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+
+        for &(start, end) in &[(0, 0), (0, 103), (10, 20), (20, 30), (25, 80), (100, 103)] {
+            let expected = &data[start..end];
+            let ranges = track_assert_some!(coder.fragment_ranges(start..end, data.len()), Failed);
+            let read = ranges
+                .iter()
+                .flat_map(|r| encoded[r.index][r.range.clone()].iter().cloned())
+                .collect::<Vec<_>>();
+            assert_eq!(read, expected);
+
+            for fragments in &[&encoded[..], &encoded[1..], &encoded[2..]] {
+                assert_eq!(track!(coder.decode_range(start..end, fragments))?, expected);
+            }
+        }
+        assert_eq!(coder.fragment_ranges(100..104, data.len()), None);
+        assert_eq!(
+            coder
+                .decode_range(100..104, &encoded[..])
+                .map_err(|e| *e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn update_parity_works() -> Result<(), MainError> {
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?;
+        let mut data = test_data(103);
+        let old = track!(coder.encode(&data))?;
+
+        // Overwrites a part of the second data fragment
+        data[30..40].copy_from_slice(&[0; 10]);
+        let new = track!(coder.encode(&data))?;
+
+        let parities = old[4..].iter().map(|f| &f[..]).collect::<Vec<_>>();
+        assert_eq!(
+            track!(coder.update_parity(1, &old[1], &new[1], &parities))?,
+            new[4..].to_vec()
+        );
+        assert_eq!(
+            coder
+                .update_parity(2, &old[1], &new[1], &parities)
+                .map_err(|e| *e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fragments_of_other_coders_are_rejected() -> Result<(), MainError> {
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(6)?, non_zero(3)?))?;
+        let encoded = track!(coder.encode(&test_data(1000)))?;
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+
+        // The headers do not identify the coders, so the fragments are regarded as corrupted
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?;
+        assert_eq!(
+            coder.decode(&fragments[..4]).map_err(|e| *e.kind()),
+            Err(ErrorKind::CorruptedFragments)
+        );
+        assert_eq!(
+            coder.decode(&fragments[5..]).map_err(|e| *e.kind()),
+            Err(ErrorKind::CorruptedFragments)
+        );
+        Ok(())
+    }
+}
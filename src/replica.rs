@@ -2,8 +2,11 @@
 //!
 //! [`ErasureCode`]: ../trait.ErasureCode.html
 use std::num::NonZeroUsize;
+use std::ops::Range;
 
-use crate::{BuildCoder, ErasureCode, ErrorKind, Fragment, FragmentBuf, Result};
+use crate::{
+    BuildCoder, ErasureCode, ErrorKind, Fragment, FragmentBuf, FragmentInfo, FragmentRange, Result,
+};
 
 /// An [`ErasureCode`] implementation that simply replicates the input data.
 ///
@@ -71,6 +74,24 @@ impl ErasureCode for ReplicaCoder {
         Ok(fragments)
     }
 
+    /// Returns the metadata of the given fragment.
+    ///
+    /// Since the fragments produced by `ReplicaCoder` have no headers,
+    /// the index is unknown and the size of the original data is known only for non-empty fragments.
+    fn fragment_info(&self, fragment: &Fragment) -> Result<FragmentInfo> {
+        Ok(FragmentInfo {
+            index: None,
+            data_len: if fragment.is_empty() {
+                None
+            } else {
+                Some(fragment.len())
+            },
+            payload_len: fragment.len(),
+            checksum: None,
+            backend: "replica".to_owned(),
+        })
+    }
+
     fn decode(&mut self, fragments: &[&Fragment]) -> Result<Vec<u8>> {
         track_assert!(
             fragments.len() >= self.data_fragments.get(),
@@ -86,6 +107,49 @@ impl ErasureCode for ReplicaCoder {
         );
         Ok(data.to_vec())
     }
+
+    fn fragment_ranges(&self, range: Range<usize>, data_len: usize) -> Option<Vec<FragmentRange>> {
+        if range.start > range.end || range.end > data_len {
+            return None;
+        }
+        Some(vec![FragmentRange::new(0, range)])
+    }
+
+    fn decode_indexed(&mut self, fragments: &[(usize, &Fragment)]) -> Result<Vec<u8>> {
+        track_assert!(
+            fragments.len() >= self.data_fragments.get(),
+            ErrorKind::InvalidInput,
+            "fragments={}, data_fragments={}",
+            fragments.len(),
+            self.data_fragments
+        );
+        let data = track_assert_some!(
+            fragments
+                .iter()
+                .find(|&&(i, _)| i == 0 || i >= self.data_fragments.get()),
+            ErrorKind::InvalidInput,
+            "No replica fragment is given"
+        );
+        Ok(data.1.to_vec())
+    }
+
+    fn reconstruct_indexed(
+        &mut self,
+        index: usize,
+        fragments: &[(usize, &Fragment)],
+    ) -> Result<Vec<u8>> {
+        track_assert!(
+            index < self.fragments().get(),
+            ErrorKind::Other,
+            "Too large index: index={}, fragments={}",
+            index,
+            self.fragments()
+        );
+        if index != 0 && index < self.data_fragments.get() {
+            return Ok(Vec::new());
+        }
+        track!(self.decode_indexed(fragments))
+    }
 }
 impl BuildCoder for ReplicaCoder {
     type Coder = Self;
@@ -98,12 +162,38 @@ impl BuildCoder for ReplicaCoder {
         format!("replica:{}:{}", self.data_fragments, self.parity_fragments)
     }
 }
+#[cfg(feature = "serde")]
+impl serde::Serialize for ReplicaCoder {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let repr = crate::serialize::FragmentsRepr::new(self.data_fragments, self.parity_fragments);
+        serde::Serialize::serialize(&repr, serializer)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ReplicaCoder {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let repr: crate::serialize::FragmentsRepr = crate::serialize::deserialize(deserializer)?;
+        Ok(ReplicaCoder::new(
+            repr.data_fragments,
+            repr.parity_fragments,
+        ))
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use std::num::NonZeroUsize;
+    use std::result::Result;
+    use trackable::error::MainError;
 
     use super::*;
+    use crate::test_util::non_zero;
     use crate::{ErasureCode, ErrorKind};
 
     #[test]
@@ -124,4 +214,69 @@ mod tests {
             coder.decode(&encoded[3..]).map_err(|e| *e.kind())
         );
     }
+
+    #[test]
+    fn decode_indexed_works() -> Result<(), MainError> {
+        let mut coder = ReplicaCoder::new(non_zero(4)?, non_zero(2)?);
+        let data = vec![0, 1, 2, 3];
+        let encoded = track!(coder.encode(&data))?;
+        let indexed = encoded
+            .iter()
+            .map(|f| &f[..])
+            .enumerate()
+            .collect::<Vec<_>>();
+
+        assert_eq!(track!(coder.decode_indexed(&indexed[2..]))?, data);
+        assert_eq!(
+            track!(coder.reconstruct_indexed(5, &indexed[1..5]))?,
+            encoded[5]
+        );
+        assert_eq!(
+            track!(coder.reconstruct_indexed(1, &indexed[2..]))?,
+            encoded[1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fragment_info_works() -> Result<(), MainError> {
+        let mut coder = ReplicaCoder::new(non_zero(4)?, non_zero(2)?);
+        let data = vec![0, 1, 2, 3];
+        let encoded = track!(coder.encode(&data))?;
+
+        let info = track!(coder.fragment_info(&encoded[4]))?;
+        assert_eq!((info.index, info.data_len), (None, Some(data.len())));
+        let info = track!(coder.fragment_info(&encoded[1]))?;
+        assert_eq!((info.index, info.data_len), (None, None));
+        Ok(())
+    }
+
+    #[test]
+    fn encode_into_works() -> Result<(), MainError> {
+        let mut coder = ReplicaCoder::new(non_zero(4)?, non_zero(2)?);
+        let data = vec![0, 1, 2, 3];
+        let encoded = track!(coder.encode(&data))?;
+
+        // The fragments have different sizes, so `encode_into` needs buffers of the sizes of `encode`
+        assert_eq!(coder.fragment_size(data.len()), None);
+        let mut buffers = encoded
+            .iter()
+            .map(|f| vec![0xFF; f.len()])
+            .collect::<Vec<_>>();
+        {
+            let mut buffers = buffers.iter_mut().map(|b| &mut b[..]).collect::<Vec<_>>();
+            track!(coder.encode_into(&data, &mut buffers))?;
+        }
+        assert_eq!(buffers, encoded);
+
+        let mut buffers = vec![vec![0; data.len() + 1]; 6];
+        let mut buffers = buffers.iter_mut().map(|b| &mut b[..]).collect::<Vec<_>>();
+        assert_eq!(
+            coder
+                .encode_into(&data, &mut buffers)
+                .map_err(|e| *e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+        Ok(())
+    }
 }
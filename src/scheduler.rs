@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::executor::Task;
+
+/// The priority of an operation of [`ErasureCoderPool`].
+///
+/// The queued operations of higher priorities are executed first,
+/// and the operations of the same priority are executed in FIFO order.
+///
+/// [`ErasureCoderPool`]: ./struct.ErasureCoderPool.html
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// The priority for latency sensitive operations (e.g., user reads and writes).
+    ///
+    /// This is the default priority.
+    #[default]
+    Foreground,
+
+    /// The priority for maintenance operations (e.g., repair).
+    Background,
+
+    /// The priority for operations that should be executed only when nothing else is queued (e.g., scrubbing).
+    Idle,
+}
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::Foreground, Priority::Background, Priority::Idle];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Schedules the operations of a pool by their priorities.
+///
+/// Each queued operation is accompanied by a task submitted to the executor of the pool,
+/// which runs the operation of the highest priority at the time it is executed (see `run`).
+///
+/// The operations other than `Priority::Foreground` are "background" operations,
+/// and the number of background operations executed concurrently can be limited.
+/// If a task finds only background operations that exceed the limit, it is deferred and
+/// the operations are executed by the task of a background operation after the operation finishes.
+pub(crate) struct Scheduler {
+    max_background: Option<NonZeroUsize>,
+    state: Mutex<State>,
+}
+impl Scheduler {
+    pub fn new(max_background: Option<NonZeroUsize>) -> Self {
+        Scheduler {
+            max_background,
+            state: Mutex::new(State {
+                queues: Default::default(),
+                running_background: 0,
+                deferred: 0,
+            }),
+        }
+    }
+
+    /// Queues an operation.
+    ///
+    /// The operation must not panic, otherwise the counter of the running background operations is left raised.
+    ///
+    /// The caller must submit a task that calls `run` to the executor after calling this.
+    pub fn push(&self, priority: Priority, operation: Task) {
+        self.lock().queues[priority.index()].push_back(operation);
+    }
+
+    /// Runs the queued operation of the highest priority.
+    pub fn run(&self) {
+        let mut state = self.lock();
+        loop {
+            let (operation, background) = if let Some(x) = self.pop(&mut state) {
+                x
+            } else {
+                state.deferred += 1;
+                return;
+            };
+            drop(state);
+
+            operation();
+            if !background {
+                return;
+            }
+
+            state = self.lock();
+            state.running_background -= 1;
+            if state.deferred == 0 {
+                return;
+            }
+            state.deferred -= 1;
+        }
+    }
+
+    fn pop(&self, state: &mut State) -> Option<(Task, bool)> {
+        for &priority in &Priority::ALL {
+            let background = priority != Priority::Foreground;
+            if background
+                && self
+                    .max_background
+                    .is_some_and(|max| state.running_background >= max.get())
+            {
+                return None;
+            }
+            if let Some(operation) = state.queues[priority.index()].pop_front() {
+                if background {
+                    state.running_background += 1;
+                }
+                return Some((operation, background));
+            }
+        }
+        None
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Scheduler")
+            .field("max_background", &self.max_background)
+            .field(
+                "queued",
+                &state.queues.iter().map(|q| q.len()).collect::<Vec<_>>(),
+            )
+            .field("running_background", &state.running_background)
+            .finish()
+    }
+}
+
+struct State {
+    queues: [VecDeque<Task>; 3],
+    running_background: usize,
+
+    /// The number of the tasks that have returned without running any operations.
+    deferred: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+        mutex.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[test]
+    fn scheduler_works() {
+        let scheduler = Arc::new(Scheduler::new(NonZeroUsize::new(1)));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let push = |priority, name: &'static str| {
+            let log = Arc::clone(&log);
+            scheduler.push(priority, Box::new(move || lock(&log).push(name)));
+        };
+        push(Priority::Idle, "idle");
+
+        // The task executed while "background0" is running is deferred due to the limit
+        let scheduler0 = Arc::clone(&scheduler);
+        let log0 = Arc::clone(&log);
+        scheduler.push(
+            Priority::Background,
+            Box::new(move || {
+                lock(&log0).push("background0");
+                scheduler0.run();
+            }),
+        );
+        push(Priority::Background, "background1");
+        push(Priority::Foreground, "foreground");
+
+        scheduler.run();
+        assert_eq!(*lock(&log), ["foreground"]);
+        scheduler.run();
+        assert_eq!(*lock(&log), ["foreground", "background0", "background1"]);
+        scheduler.run();
+        assert_eq!(
+            *lock(&log),
+            ["foreground", "background0", "background1", "idle"]
+        );
+        assert_eq!(scheduler.lock().deferred, 0);
+    }
+}
@@ -0,0 +1,203 @@
+//! Helpers for the serialized representations of coder configurations.
+//!
+//! Every representation is a map that has a `version` field followed by the parameters of the coder.
+//! The `version` is incremented when the representation is changed in an incompatible way.
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+
+/// The current version of the serialized representations.
+pub(crate) const VERSION: u32 = 1;
+
+/// Checks that the deserialized `version` is supported by this crate.
+fn check_version<E: serde::de::Error>(version: u32) -> Result<(), E> {
+    if version != VERSION {
+        return Err(E::custom(format!(
+            "Unsupported version: expected={}, actual={}",
+            VERSION, version
+        )));
+    }
+    Ok(())
+}
+
+/// A representation that has the `version` field.
+pub(crate) trait Versioned {
+    fn version(&self) -> u32;
+}
+
+/// Deserializes a representation and checks that its version is supported by this crate.
+pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de> + Versioned,
+    D: serde::Deserializer<'de>,
+{
+    let repr = T::deserialize(deserializer)?;
+    check_version(repr.version())?;
+    Ok(repr)
+}
+
+/// Converts an error that occurred during building a coder into a deserialization error.
+pub(crate) fn invalid<E: serde::de::Error>(e: crate::Error) -> E {
+    E::custom(e)
+}
+
+/// The representation of the coders parameterized only by the numbers of data and parity fragments.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct FragmentsRepr {
+    pub version: u32,
+    pub data_fragments: NonZeroUsize,
+    pub parity_fragments: NonZeroUsize,
+}
+impl FragmentsRepr {
+    pub fn new(data_fragments: NonZeroUsize, parity_fragments: NonZeroUsize) -> Self {
+        FragmentsRepr {
+            version: VERSION,
+            data_fragments,
+            parity_fragments,
+        }
+    }
+}
+impl Versioned for FragmentsRepr {
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+/// The representation of [`LrcCoder`](../lrc/struct.LrcCoder.html).
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LrcRepr {
+    pub version: u32,
+    pub data_fragments: NonZeroUsize,
+    pub local_groups: NonZeroUsize,
+    pub global_parity_fragments: usize,
+}
+impl Versioned for LrcRepr {
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+/// The representation of the liberasurecode compatible coder builders.
+///
+/// `backend` and `checksum` are the names of the variants of the corresponding enums.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LibErasureCodeRepr {
+    pub version: u32,
+    pub data_fragments: NonZeroUsize,
+    pub parity_fragments: NonZeroUsize,
+    pub backend: String,
+    pub checksum: String,
+}
+impl Versioned for LibErasureCodeRepr {
+    fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::{Failed, Failure, MainError};
+
+    use crate::clay::ClayCoder;
+    use crate::lrc::LrcCoder;
+    use crate::pure_liberasurecode::{Backend, Checksum, PureLibErasureCoderBuilder};
+    use crate::reedsolomon::ReedSolomonCoder;
+    use crate::replica::ReplicaCoder;
+    use crate::spec::CoderSpec;
+    use crate::test_util::non_zero;
+    use crate::BuildCoder;
+
+    fn round_trip<B>(builder: &B) -> Result<String, Failure>
+    where
+        B: BuildCoder + serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let json = track_any_err!(serde_json::to_string(builder))?;
+        let deserialized: B = track_any_err!(serde_json::from_str(&json))?;
+        track_assert_eq!(deserialized.coder_id(), builder.coder_id(), Failed);
+        Ok(json)
+    }
+
+    #[test]
+    fn round_trip_works() -> Result<(), MainError> {
+        let k = non_zero(4)?;
+        let m = non_zero(2)?;
+
+        assert_eq!(
+            track!(round_trip(&ReplicaCoder::new(k, m)))?,
+            r#"{"version":1,"data_fragments":4,"parity_fragments":2}"#
+        );
+        track!(round_trip(&track!(ReedSolomonCoder::new(k, m))?))?;
+        track!(round_trip(&track!(ClayCoder::new(k, m))?))?;
+        assert_eq!(
+            track!(round_trip(&track!(LrcCoder::new(k, m, 1))?))?,
+            r#"{"version":1,"data_fragments":4,"local_groups":2,"global_parity_fragments":1}"#
+        );
+        assert_eq!(
+            track!(round_trip(
+                &PureLibErasureCoderBuilder::new(k, m)
+                    .backend(Backend::IsaLRsVand)
+                    .checksum(Checksum::Crc32)
+            ))?,
+            r#"{"version":1,"data_fragments":4,"parity_fragments":2,"backend":"IsaLRsVand","checksum":"Crc32"}"#
+        );
+        #[cfg(all(unix, feature = "liberasurecode"))]
+        {
+            use crate::liberasurecode::{self, LibErasureCoderBuilder};
+
+            let backends = [
+                (liberasurecode::Backend::JerasureRsVand, "JerasureRsVand"),
+                (
+                    liberasurecode::Backend::JerasureRsCauchy,
+                    "JerasureRsCauchy",
+                ),
+            ];
+            let checksums = [
+                (liberasurecode::Checksum::None, "None"),
+                (liberasurecode::Checksum::Crc32, "Crc32"),
+                (liberasurecode::Checksum::Md5, "Md5"),
+            ];
+            for &(backend, backend_name) in &backends {
+                for &(checksum, checksum_name) in &checksums {
+                    let builder = LibErasureCoderBuilder::new(k, m)
+                        .backend(backend)
+                        .checksum(checksum);
+                    assert_eq!(
+                        track!(round_trip(&builder))?,
+                        format!(
+                            r#"{{"version":1,"data_fragments":4,"parity_fragments":2,"backend":"{}","checksum":"{}"}}"#,
+                            backend_name, checksum_name
+                        )
+                    );
+                }
+            }
+        }
+
+        let spec: CoderSpec = track!("lrc:4:2:1".parse())?;
+        let json = track_any_err!(serde_json::to_string(&spec))?;
+        assert_eq!(json, r#""lrc:4:2:1""#);
+        assert_eq!(
+            track_any_err!(serde_json::from_str::<CoderSpec>(&json))?,
+            spec
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_representations_are_rejected() {
+        let invalid = [
+            r#"{"version":2,"data_fragments":4,"parity_fragments":2}"#,
+            r#"{"data_fragments":4,"parity_fragments":2}"#,
+            r#"{"version":1,"data_fragments":0,"parity_fragments":2}"#,
+        ];
+        for json in &invalid {
+            assert!(serde_json::from_str::<ReplicaCoder>(json).is_err());
+        }
+
+        let json = r#"{"version":1,"data_fragments":4,"parity_fragments":2,"backend":"Foo","checksum":"None"}"#;
+        assert!(serde_json::from_str::<PureLibErasureCoderBuilder>(json).is_err());
+
+        let json =
+            r#"{"version":1,"data_fragments":4,"local_groups":5,"global_parity_fragments":1}"#;
+        assert!(serde_json::from_str::<LrcCoder>(json).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//! Textual specifications of coders and a registry that builds coders from those.
+//!
+//! A specification has the same format as the identifiers returned by [`BuildCoder::coder_id`]
+//! (e.g., `"liberasurecode:JerasureRsVand:Crc32:6:3"`), so the configuration of a coder can be
+//! stored in configuration files or metadata and restored later.
+//!
+//! [`BuildCoder::coder_id`]: ../trait.BuildCoder.html#tymethod.coder_id
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::clay::ClayCoder;
+use crate::envelope::EnvelopeCoder;
+use crate::lrc::LrcCoder;
+use crate::pure_liberasurecode::{self, PureLibErasureCoderBuilder};
+use crate::reedsolomon::ReedSolomonCoder;
+use crate::replica::ReplicaCoder;
+use crate::{BuildCoder, ErasureCode, Error, ErrorKind, Result};
+
+/// A coder specification that consists of a kind and parameters separated by colons.
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::spec::CoderSpec;
+///
+/// # fn main() -> Result<(), ecpool::Error> {
+/// let spec: CoderSpec = "reedsolomon:4:2".parse()?;
+/// assert_eq!(spec.kind(), "reedsolomon");
+/// assert_eq!(spec.params(), ["4", "2"]);
+/// assert_eq!(spec.to_string(), "reedsolomon:4:2");
+/// assert_eq!(spec, CoderSpec::new("reedsolomon").param(4).param(2));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CoderSpec {
+    kind: String,
+    params: Vec<String>,
+}
+impl CoderSpec {
+    /// Makes a new `CoderSpec` instance that has no parameters.
+    pub fn new<T: Into<String>>(kind: T) -> Self {
+        CoderSpec {
+            kind: kind.into(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Makes a new `CoderSpec` instance from the identifier of the coders built by the given builder.
+    pub fn from_builder<B: BuildCoder>(builder: &B) -> Result<Self> {
+        track!(builder.coder_id().parse())
+    }
+
+    /// Appends a parameter to the specification.
+    pub fn param<T: ToString>(mut self, param: T) -> Self {
+        self.params.push(param.to_string());
+        self
+    }
+
+    /// Returns the kind of the coder.
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the parameters of the coder.
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// Parses the `i`-th parameter.
+    ///
+    /// If the parameter does not exist or cannot be parsed,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn parse_param<T: FromStr>(&self, i: usize) -> Result<T> {
+        let param = track_assert_some!(
+            self.params.get(i),
+            ErrorKind::InvalidInput,
+            "Missing parameter: spec={}, i={}",
+            self,
+            i
+        );
+        let value = track_assert_some!(
+            param.parse().ok(),
+            ErrorKind::InvalidInput,
+            "Invalid parameter: spec={}, i={}",
+            self,
+            i
+        );
+        Ok(value)
+    }
+
+    /// Checks that the number of the parameters is `n`.
+    ///
+    /// If not, this will return an `ErrorKind::InvalidInput` error.
+    pub fn check_params(&self, n: usize) -> Result<()> {
+        track_assert_eq!(
+            self.params.len(),
+            n,
+            ErrorKind::InvalidInput,
+            "Unexpected number of parameters: spec={}",
+            self
+        );
+        Ok(())
+    }
+}
+impl FromStr for CoderSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut tokens = s.split(':');
+        let kind = tokens.next().unwrap_or("");
+        track_assert!(
+            !kind.is_empty(),
+            ErrorKind::InvalidInput,
+            "Empty coder kind: {:?}",
+            s
+        );
+        let params = tokens.map(|t| t.to_owned()).collect::<Vec<_>>();
+        track_assert!(
+            params.iter().all(|p| !p.is_empty()),
+            ErrorKind::InvalidInput,
+            "Empty parameter: {:?}",
+            s
+        );
+        Ok(CoderSpec {
+            kind: kind.to_owned(),
+            params,
+        })
+    }
+}
+impl fmt::Display for CoderSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        for param in &self.params {
+            write!(f, ":{}", param)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CoderSpec {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CoderSpec {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = serde::Deserialize::deserialize(deserializer)?;
+        s.parse().map_err(crate::serialize::invalid)
+    }
+}
+
+const ENVELOPE_KIND: &str = "envelope";
+
+type Factory = Arc<dyn Fn(&CoderSpec) -> Result<Box<dyn ErasureCode>> + Send + Sync>;
+
+/// A registry that builds coders from their specifications.
+///
+/// [`CoderRegistry::new`] registers the following built-in kinds:
+/// - `clay:N:M`
+/// - `envelope:INNER_SPEC` (e.g., `envelope:replica:2:1`)
+///   - The coder built from `INNER_SPEC` is wrapped in [`EnvelopeCoder`].
+///     `INNER_SPEC` can be any kind registered in the registry.
+/// - `liberasurecode:BACKEND:CHECKSUM:N:M` (if the `liberasurecode` feature is enabled)
+/// - `lrc:N:LOCAL_GROUPS:GLOBAL_PARITIES`
+/// - `pure_liberasurecode:BACKEND:CHECKSUM:N:M`
+/// - `reedsolomon:N:M`
+/// - `replica:N:M`
+///
+/// [`CoderRegistry::new`]: #method.new
+/// [`EnvelopeCoder`]: ../envelope/struct.EnvelopeCoder.html
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::spec::{CoderRegistry, CoderSpec};
+/// use ecpool::replica::ReplicaCoder;
+/// use ecpool::ErasureCode;
+///
+/// # fn main() -> Result<(), ecpool::Error> {
+/// let mut registry = CoderRegistry::new();
+/// registry.register("mirror", |spec| {
+///     spec.check_params(1)?;
+///     let copies = spec.parse_param(0)?;
+///     Ok(Box::new(ReplicaCoder::new(copies, copies)))
+/// });
+///
+/// let mut coder = registry.build(&"mirror:2".parse()?)?;
+/// assert_eq!(coder.fragments().get(), 4);
+///
+/// let mut coder = registry.build(&CoderSpec::new("reedsolomon").param(4).param(2))?;
+/// let encoded = coder.encode(b"foo")?;
+/// assert_eq!(coder.decode_indexed(&[(1, &encoded[1]), (2, &encoded[2]), (4, &encoded[4]), (5, &encoded[5])])?, b"foo");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CoderRegistry {
+    factories: HashMap<String, Factory>,
+}
+impl CoderRegistry {
+    /// Makes a new `CoderRegistry` instance that has the built-in kinds.
+    pub fn new() -> Self {
+        let mut this = Self::empty();
+        this.register("clay", |spec| {
+            let (k, m) = track!(data_and_parity_fragments(spec, 0))?;
+            track!(spec.check_params(2))?;
+            Ok(Box::new(track!(ClayCoder::new(k, m))?))
+        });
+        #[cfg(all(unix, feature = "liberasurecode"))]
+        this.register("liberasurecode", |spec| {
+            use crate::liberasurecode::{self, LibErasureCoderBuilder};
+            track!(spec.check_params(4))?;
+            let backend = track!(liberasurecode::parse_backend(&spec.params()[0]))?;
+            let checksum = track!(liberasurecode::parse_checksum(&spec.params()[1]))?;
+            let (k, m) = track!(data_and_parity_fragments(spec, 2))?;
+            let builder = LibErasureCoderBuilder::new(k, m)
+                .backend(backend)
+                .checksum(checksum);
+            Ok(Box::new(track!(builder.build_coder())?))
+        });
+        this.register("lrc", |spec| {
+            track!(spec.check_params(3))?;
+            let k = track!(spec.parse_param(0))?;
+            let local_groups = track!(spec.parse_param(1))?;
+            let global_parities = track!(spec.parse_param(2))?;
+            Ok(Box::new(track!(LrcCoder::new(
+                k,
+                local_groups,
+                global_parities
+            ))?))
+        });
+        this.register("pure_liberasurecode", |spec| {
+            track!(spec.check_params(4))?;
+            let backend = track!(spec.parse_param(0))?;
+            let checksum = track!(spec.parse_param(1))?;
+            let (k, m) = track!(data_and_parity_fragments(spec, 2))?;
+            let builder = PureLibErasureCoderBuilder::new(k, m)
+                .backend(backend)
+                .checksum(checksum);
+            let coder: pure_liberasurecode::PureLibErasureCoder = track!(builder.build_coder())?;
+            Ok(Box::new(coder))
+        });
+        this.register("reedsolomon", |spec| {
+            track!(spec.check_params(2))?;
+            let (k, m) = track!(data_and_parity_fragments(spec, 0))?;
+            Ok(Box::new(track!(ReedSolomonCoder::new(k, m))?))
+        });
+        this.register("replica", |spec| {
+            track!(spec.check_params(2))?;
+            let (k, m) = track!(data_and_parity_fragments(spec, 0))?;
+            Ok(Box::new(ReplicaCoder::new(k, m)))
+        });
+        this
+    }
+
+    /// Makes a new `CoderRegistry` instance that has no kinds.
+    pub fn empty() -> Self {
+        CoderRegistry {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registers the factory function of the given kind of coders.
+    ///
+    /// If the kind has already been registered, the factory is replaced with the new one.
+    /// Note that the `envelope` kind is always handled by the registry itself.
+    pub fn register<F>(&mut self, kind: &str, factory: F)
+    where
+        F: Fn(&CoderSpec) -> Result<Box<dyn ErasureCode>> + Send + Sync + 'static,
+    {
+        self.factories.insert(kind.to_owned(), Arc::new(factory));
+    }
+
+    /// Returns the registered kinds (in arbitrary order).
+    pub fn kinds(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(|k| k.as_str())
+    }
+
+    /// Builds a coder from the given specification.
+    ///
+    /// If the kind of the specification is not registered,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    pub fn build(&self, spec: &CoderSpec) -> Result<Box<dyn ErasureCode>> {
+        if spec.kind() == ENVELOPE_KIND {
+            let inner: CoderSpec = track!(spec.params().join(":").parse())?;
+            let coder = track!(self.build(&inner))?;
+            return Ok(Box::new(EnvelopeCoder::new(coder, &inner.to_string())));
+        }
+        let factory = track_assert_some!(
+            self.factories.get(spec.kind()),
+            ErrorKind::InvalidInput,
+            "Unknown coder kind: spec={}",
+            spec
+        );
+        track!(factory(spec))
+    }
+
+    /// Makes a [`BuildCoder`] implementation that builds coders from the given specification.
+    ///
+    /// It can be used to make an [`ErasureCoderPool`].
+    /// If the specification is invalid, this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// [`BuildCoder`]: ../trait.BuildCoder.html
+    /// [`ErasureCoderPool`]: ../struct.ErasureCoderPool.html
+    pub fn builder(&self, spec: CoderSpec) -> Result<SpecCoderBuilder> {
+        track!(self.build(&spec))?;
+        Ok(SpecCoderBuilder {
+            registry: self.clone(),
+            spec,
+        })
+    }
+}
+impl Default for CoderRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl fmt::Debug for CoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut kinds = self.kinds().collect::<Vec<_>>();
+        kinds.sort_unstable();
+        f.debug_struct("CoderRegistry")
+            .field("kinds", &kinds)
+            .finish()
+    }
+}
+
+/// A [`BuildCoder`] implementation that builds coders from a specification by using a [`CoderRegistry`].
+///
+/// This is created by [`CoderRegistry::builder`].
+/// The identifier of the coders is the textual representation of the specification.
+///
+/// [`BuildCoder`]: ../trait.BuildCoder.html
+/// [`CoderRegistry`]: ./struct.CoderRegistry.html
+/// [`CoderRegistry::builder`]: ./struct.CoderRegistry.html#method.builder
+#[derive(Debug, Clone)]
+pub struct SpecCoderBuilder {
+    registry: CoderRegistry,
+    spec: CoderSpec,
+}
+impl SpecCoderBuilder {
+    /// Returns the specification of the coders.
+    pub fn spec(&self) -> &CoderSpec {
+        &self.spec
+    }
+}
+impl BuildCoder for SpecCoderBuilder {
+    type Coder = Box<dyn ErasureCode>;
+
+    fn build_coder(&self) -> Result<Self::Coder> {
+        track!(self.registry.build(&self.spec))
+    }
+
+    fn coder_id(&self) -> String {
+        self.spec.to_string()
+    }
+}
+
+fn data_and_parity_fragments(
+    spec: &CoderSpec,
+    offset: usize,
+) -> Result<(NonZeroUsize, NonZeroUsize)> {
+    let k = track!(spec.parse_param(offset))?;
+    let m = track!(spec.parse_param(offset + 1))?;
+    Ok((k, m))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::MainError;
+
+    use super::*;
+    use crate::envelope::EnvelopeCoderBuilder;
+    use crate::pure_liberasurecode::{Backend, Checksum};
+    use crate::test_util::non_zero;
+    use crate::ErasureCoderPool;
+
+    #[test]
+    fn builtin_coder_ids_are_round_trip() -> Result<(), MainError> {
+        let k = non_zero(4)?;
+        let m = non_zero(2)?;
+        let coder_ids = vec![
+            track!(ClayCoder::new(k, m))?.coder_id(),
+            EnvelopeCoderBuilder::new(ReplicaCoder::new(k, m)).coder_id(),
+            track!(LrcCoder::new(k, m, 2))?.coder_id(),
+            PureLibErasureCoderBuilder::new(k, m)
+                .backend(Backend::IsaLRsVand)
+                .checksum(Checksum::Crc32)
+                .coder_id(),
+            track!(ReedSolomonCoder::new(k, m))?.coder_id(),
+            ReplicaCoder::new(k, m).coder_id(),
+        ];
+
+        let registry = CoderRegistry::new();
+        for coder_id in coder_ids {
+            let spec: CoderSpec = track!(coder_id.parse())?;
+            assert_eq!(spec.to_string(), coder_id);
+            let builder = track!(registry.builder(spec))?;
+            assert_eq!(builder.coder_id(), coder_id);
+            let mut coder = track!(builder.build_coder())?;
+            let encoded = track!(coder.encode(b"foo"))?;
+            let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+            assert_eq!(track!(coder.decode(&fragments))?, b"foo");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_specs_are_rejected() -> Result<(), MainError> {
+        let registry = CoderRegistry::new();
+        for spec in &["", ":4:2", "replica::2"] {
+            assert_eq!(
+                spec.parse::<CoderSpec>().map_err(|e| *e.kind()),
+                Err(ErrorKind::InvalidInput)
+            );
+        }
+        for spec in &[
+            "unknown:4:2",
+            "replica:4",
+            "replica:4:0",
+            "reedsolomon:4:2:1",
+            "pure_liberasurecode:Unknown:None:4:2",
+        ] {
+            let spec = track!(spec.parse())?;
+            assert_eq!(
+                registry.build(&spec).map(|_| ()).map_err(|e| *e.kind()),
+                Err(ErrorKind::InvalidInput)
+            );
+        }
+
+        let spec = track!("replica:4:2".parse())?;
+        let pool = ErasureCoderPool::new(track!(registry.builder(spec))?);
+        let encoded = track!(fibers_global::execute(pool.encode(vec![1, 2, 3])))?;
+        assert_eq!(
+            track!(fibers_global::execute(pool.decode(encoded)))?,
+            vec![1, 2, 3]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,718 @@
+//! Streaming encoding and decoding of objects that are too large to be held in memory.
+//!
+//! An object is split into fixed-size stripes and each stripe is encoded independently.
+//! The fragments of the stripes are appended to `N + M` fragment sinks,
+//! and the resulting [`StripeIndex`] describes where each stripe is stored.
+//!
+//! [`StripeEncoder`] encodes an object and [`StripeDecoder`] decodes it from the fragment sources.
+//!
+//! [`StripeIndex`]: ./struct.StripeIndex.html
+//! [`StripeEncoder`]: ./struct.StripeEncoder.html
+//! [`StripeDecoder`]: ./struct.StripeDecoder.html
+use fibers::{Executor, InPlaceExecutor};
+use futures::{Async, Future, Poll, Stream};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::num::NonZeroUsize;
+use trackable::error::ErrorKindExt;
+
+use crate::{BuildCoder, ErasureCoderPool, Error, ErrorKind, FragmentBuf, Result};
+
+/// The location of a stripe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripeEntry {
+    /// The offset of the stripe in the original object.
+    pub data_offset: u64,
+
+    /// The size of the stripe in the original object.
+    pub data_len: usize,
+
+    /// The offsets of the fragments of the stripe in the respective fragment sinks.
+    pub fragment_offsets: Vec<u64>,
+
+    /// The sizes of the fragments of the stripe.
+    pub fragment_lens: Vec<usize>,
+}
+
+/// The index of the stripes of an object.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StripeIndex {
+    /// The stripes in the order of their offsets.
+    pub stripes: Vec<StripeEntry>,
+}
+impl StripeIndex {
+    /// Makes a new empty `StripeIndex` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the given encoded stripe to the index.
+    ///
+    /// The fragments of the stripe are regarded as being stored immediately after
+    /// those of the preceding stripe in the respective fragment sinks.
+    pub fn push(&mut self, stripe: &EncodedStripe) {
+        let fragment_offsets = match self.stripes.last() {
+            None => vec![0; stripe.fragments.len()],
+            Some(last) => last
+                .fragment_offsets
+                .iter()
+                .zip(last.fragment_lens.iter())
+                .map(|(&offset, &len)| offset + len as u64)
+                .collect(),
+        };
+        self.stripes.push(StripeEntry {
+            data_offset: stripe.data_offset,
+            data_len: stripe.data_len,
+            fragment_offsets,
+            fragment_lens: stripe.fragments.iter().map(|f| f.len()).collect(),
+        });
+    }
+
+    /// Returns the size of the original object.
+    pub fn data_len(&self) -> u64 {
+        self.stripes
+            .last()
+            .map_or(0, |s| s.data_offset + s.data_len as u64)
+    }
+}
+
+/// An encoded stripe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedStripe {
+    /// The offset of the stripe in the original object.
+    pub data_offset: u64,
+
+    /// The size of the stripe in the original object.
+    pub data_len: usize,
+
+    /// The fragments of the stripe.
+    pub fragments: Vec<FragmentBuf>,
+}
+
+/// Encoder that splits an object into fixed-size stripes and encodes each stripe via [`ErasureCoderPool`].
+///
+/// At most one stripe is buffered and encoded at a time, so the memory usage is bounded by the stripe size
+/// regardless of the size of the object.
+///
+/// [`ErasureCoderPool`]: ../struct.ErasureCoderPool.html
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::{ErasureCode, ErasureCoderPool};
+/// use ecpool::reedsolomon::ReedSolomonCoder;
+/// use ecpool::stripe::StripeEncoder;
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data_fragments = NonZeroUsize::new(4).ok_or("invalid input")?;
+/// let parity_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let mut coder = ReedSolomonCoder::new(data_fragments, parity_fragments)?;
+/// let pool = ErasureCoderPool::new(coder.clone());
+///
+/// // Encodes an object by 1000 bytes stripes
+/// let data = (0..2500).map(|i| i as u8).collect::<Vec<_>>();
+/// let encoder = StripeEncoder::new(pool, NonZeroUsize::new(1000).ok_or("invalid input")?);
+/// let mut sinks = vec![Vec::new(); 6];
+/// let index = encoder.encode_reader(&data[..], &mut sinks)?;
+/// assert_eq!(index.stripes.len(), 3);
+/// assert_eq!(index.data_len(), 2500);
+///
+/// // Decodes the last stripe
+/// let stripe = &index.stripes[2];
+/// let fragments = (0..6)
+///     .map(|i| {
+///         let offset = stripe.fragment_offsets[i] as usize;
+///         &sinks[i][offset..offset + stripe.fragment_lens[i]]
+///     })
+///     .collect::<Vec<_>>();
+/// assert_eq!(coder.decode(&fragments[2..])?, &data[2000..]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StripeEncoder<B> {
+    pool: ErasureCoderPool<B>,
+    stripe_size: NonZeroUsize,
+}
+impl<B: BuildCoder> StripeEncoder<B> {
+    /// Makes a new `StripeEncoder` instance.
+    pub fn new(pool: ErasureCoderPool<B>, stripe_size: NonZeroUsize) -> Self {
+        StripeEncoder { pool, stripe_size }
+    }
+
+    /// Returns the size of a stripe.
+    pub fn stripe_size(&self) -> NonZeroUsize {
+        self.stripe_size
+    }
+
+    /// Reads an object from `reader` until EOF, encodes it and writes the fragments to `sinks`.
+    ///
+    /// The fragments of the `i`-th index are appended to `sinks[i]`.
+    /// If the number of the sinks differs from the number of the fragments,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    ///
+    /// Note that this blocks the current thread until the whole object is encoded,
+    /// so it must not be called on threads that execute fibers.
+    pub fn encode_reader<R, W>(&self, mut reader: R, sinks: &mut [W]) -> Result<StripeIndex>
+    where
+        R: Read,
+        W: Write,
+    {
+        // The futures returned by the pool must be polled in a fibers context.
+        let mut executor = track!(InPlaceExecutor::new().map_err(|e| ErrorKind::Other.cause(e)))?;
+        let mut index = StripeIndex::new();
+        let mut data_offset = 0;
+        loop {
+            let stripe = track!(read_stripe(&mut reader, self.stripe_size.get()))?;
+            if stripe.is_empty() {
+                break;
+            }
+            let data_len = stripe.len();
+            let fragments = track!(executor
+                .run_future(self.pool.encode(stripe))
+                .map_err(|e| ErrorKind::Other.cause(e)))?;
+            let fragments = track!(fragments)?;
+            track_assert_eq!(
+                fragments.len(),
+                sinks.len(),
+                ErrorKind::InvalidInput,
+                "The number of sinks differs from the number of fragments"
+            );
+            for (fragment, sink) in fragments.iter().zip(sinks.iter_mut()) {
+                track!(sink
+                    .write_all(fragment)
+                    .map_err(|e| ErrorKind::Other.cause(e)))?;
+            }
+            let stripe = EncodedStripe {
+                data_offset,
+                data_len,
+                fragments,
+            };
+            index.push(&stripe);
+            data_offset += data_len as u64;
+            if data_len < self.stripe_size.get() {
+                break;
+            }
+        }
+        for sink in sinks {
+            track!(sink.flush().map_err(|e| ErrorKind::Other.cause(e)))?;
+        }
+        Ok(index)
+    }
+
+    /// Returns a stream that encodes the object consisting of the given chunks stripe by stripe.
+    ///
+    /// The chunks may have arbitrary sizes.
+    pub fn encode_stream<S>(&self, chunks: S) -> EncodeStripes<B, S>
+    where
+        S: Stream<Error = Error>,
+        S::Item: AsRef<[u8]>,
+    {
+        EncodeStripes {
+            pool: self.pool.clone(),
+            stripe_size: self.stripe_size.get(),
+            chunks,
+            eos: false,
+            buf: Vec::new(),
+            data_offset: 0,
+            encoding: None,
+        }
+    }
+}
+
+type EncodeFuture = Box<dyn Future<Item = Vec<FragmentBuf>, Error = Error> + Send>;
+
+/// A stream that encodes an object stripe by stripe.
+///
+/// This is created by calling [`StripeEncoder::encode_stream`] method.
+///
+/// [`StripeEncoder::encode_stream`]: ./struct.StripeEncoder.html#method.encode_stream
+pub struct EncodeStripes<B, S> {
+    pool: ErasureCoderPool<B>,
+    stripe_size: usize,
+    chunks: S,
+    eos: bool,
+    buf: Vec<u8>,
+    data_offset: u64,
+    encoding: Option<(usize, EncodeFuture)>,
+}
+impl<B, S> Stream for EncodeStripes<B, S>
+where
+    B: BuildCoder,
+    S: Stream<Error = Error>,
+    S::Item: AsRef<[u8]>,
+{
+    type Item = EncodedStripe;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if let Some((data_len, future)) = self.encoding.as_mut() {
+            let data_len = *data_len;
+            if let Async::Ready(fragments) = track!(future.poll())? {
+                self.encoding = None;
+                let stripe = EncodedStripe {
+                    data_offset: self.data_offset,
+                    data_len,
+                    fragments,
+                };
+                self.data_offset += data_len as u64;
+                return Ok(Async::Ready(Some(stripe)));
+            } else {
+                return Ok(Async::NotReady);
+            }
+        }
+
+        while !self.eos && self.buf.len() < self.stripe_size {
+            match track!(self.chunks.poll())? {
+                Async::NotReady => return Ok(Async::NotReady),
+                Async::Ready(None) => self.eos = true,
+                Async::Ready(Some(chunk)) => self.buf.extend_from_slice(chunk.as_ref()),
+            }
+        }
+        if self.buf.is_empty() {
+            return Ok(Async::Ready(None));
+        }
+        let rest = if self.buf.len() > self.stripe_size {
+            self.buf.split_off(self.stripe_size)
+        } else {
+            Vec::new()
+        };
+        let stripe = mem::replace(&mut self.buf, rest);
+        self.encoding = Some((stripe.len(), Box::new(self.pool.encode(stripe))));
+        self.poll()
+    }
+}
+
+/// Decoder that decodes a striped object from the fragment sources stripe by stripe
+/// via [`ErasureCoderPool`].
+///
+/// For each stripe, the fragments are read from the first `N` available sources
+/// (where `N` is the number of data fragments).
+/// If reading from a source fails, the source is abandoned and
+/// the next available one is used for the stripe and the remaining ones.
+///
+/// [`ErasureCoderPool`]: ../struct.ErasureCoderPool.html
+///
+/// # Examples
+///
+/// ```
+/// use ecpool::ErasureCoderPool;
+/// use ecpool::reedsolomon::ReedSolomonCoder;
+/// use ecpool::stripe::{StripeDecoder, StripeEncoder};
+/// use std::io::Read;
+/// use std::num::NonZeroUsize;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data_fragments = NonZeroUsize::new(4).ok_or("invalid input")?;
+/// let parity_fragments = NonZeroUsize::new(2).ok_or("invalid input")?;
+/// let pool = ErasureCoderPool::new(ReedSolomonCoder::new(data_fragments, parity_fragments)?);
+///
+/// // Encodes
+/// let data = (0..2500).map(|i| i as u8).collect::<Vec<_>>();
+/// let encoder = StripeEncoder::new(pool.clone(), NonZeroUsize::new(1000).ok_or("invalid input")?);
+/// let mut sinks = vec![Vec::new(); 6];
+/// let index = encoder.encode_reader(&data[..], &mut sinks)?;
+///
+/// // Decodes without the fragments #0 and #3
+/// let mut sources = sinks.iter().map(|s| Some(&s[..])).collect::<Vec<_>>();
+/// sources[0] = None;
+/// sources[3] = None;
+/// let decoder = StripeDecoder::new(pool);
+/// let mut decoded = Vec::new();
+/// decoder.decode_reader(index, sources)?.read_to_end(&mut decoded)?;
+/// assert_eq!(decoded, data);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StripeDecoder<B> {
+    pool: ErasureCoderPool<B>,
+    read_ahead: NonZeroUsize,
+}
+impl<B: BuildCoder> StripeDecoder<B> {
+    /// The default value of the number of stripes that are decoded concurrently.
+    pub const DEFAULT_READ_AHEAD: usize = 2;
+
+    /// Makes a new `StripeDecoder` instance.
+    ///
+    /// The number of fragments read for decoding each stripe is the number of data fragments of the coder of `pool`.
+    pub fn new(pool: ErasureCoderPool<B>) -> Self {
+        StripeDecoder {
+            pool,
+            read_ahead: NonZeroUsize::new(Self::DEFAULT_READ_AHEAD).expect("Never fails"),
+        }
+    }
+
+    /// Sets the number of stripes that are read and decoded ahead of the consumer (including the current one).
+    ///
+    /// The default value is `DEFAULT_READ_AHEAD`.
+    pub fn read_ahead(mut self, stripes: NonZeroUsize) -> Self {
+        self.read_ahead = stripes;
+        self
+    }
+
+    /// Returns a stream that yields the decoded stripes of the object described by `index`.
+    ///
+    /// `sources[i]` is the reader of the fragments of the `i`-th index (or `None` if unavailable),
+    /// which must be positioned at the beginning of the first stripe's fragment.
+    ///
+    /// If the number of the fragments of a stripe in `index` differs from the number of the sources,
+    /// this will return an `ErrorKind::InvalidInput` error.
+    /// The stream fails with `ErrorKind::InvalidInput` if the number of the sources differs from
+    /// the number of the fragments of the coder (which is queried from the pool on a thread in it).
+    ///
+    /// Note that the sources are read on the thread that polls the stream.
+    pub fn decode_stream<R: Read>(
+        &self,
+        index: StripeIndex,
+        sources: Vec<Option<R>>,
+    ) -> Result<DecodeStripes<B, R>> {
+        for (i, stripe) in index.stripes.iter().enumerate() {
+            track_assert!(
+                stripe.fragment_offsets.len() == sources.len()
+                    && stripe.fragment_lens.len() == sources.len(),
+                ErrorKind::InvalidInput,
+                "The number of fragments of the stripe differs from the number of sources: stripe={}",
+                i
+            );
+        }
+        Ok(DecodeStripes {
+            pool: self.pool.clone(),
+            fragment_counts: Box::new(self.pool.fragment_counts()),
+            data_fragments: None,
+            read_ahead: self.read_ahead.get(),
+            index,
+            sources: sources
+                .into_iter()
+                .map(|s| {
+                    s.map(|reader| Source {
+                        reader,
+                        position: 0,
+                    })
+                })
+                .collect(),
+            next_stripe: 0,
+            decoding: VecDeque::new(),
+        })
+    }
+
+    /// Returns a reader that yields the object described by `index`.
+    ///
+    /// See [`decode_stream`] for the meaning of the arguments and the errors.
+    ///
+    /// Note that reading from the result reader blocks the current thread until the stripe is decoded,
+    /// so it must not be used on threads that execute fibers.
+    ///
+    /// [`decode_stream`]: #method.decode_stream
+    pub fn decode_reader<R: Read>(
+        &self,
+        index: StripeIndex,
+        sources: Vec<Option<R>>,
+    ) -> Result<StripeReader<B, R>> {
+        Ok(StripeReader {
+            stripes: track!(self.decode_stream(index, sources))?,
+            executor: None,
+            buf: Vec::new(),
+            position: 0,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Source<R> {
+    reader: R,
+    position: u64,
+}
+impl<R: Read> Source<R> {
+    fn read_fragment(&mut self, offset: u64, len: usize) -> io::Result<FragmentBuf> {
+        if self.position > offset {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot rewind a fragment source",
+            ));
+        }
+        let skip = offset - self.position;
+        let skipped = io::copy(&mut (&mut self.reader).take(skip), &mut io::sink())?;
+        self.position += skipped;
+        if skipped < skip {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut fragment = vec![0; len];
+        self.reader.read_exact(&mut fragment)?;
+        self.position += len as u64;
+        Ok(fragment)
+    }
+}
+
+type DecodeFuture = Box<dyn Future<Item = Vec<u8>, Error = Error> + Send>;
+type FragmentCountsFuture =
+    Box<dyn Future<Item = (NonZeroUsize, NonZeroUsize), Error = Error> + Send>;
+
+/// A stream that decodes an object stripe by stripe.
+///
+/// This is created by calling [`StripeDecoder::decode_stream`] method.
+///
+/// [`StripeDecoder::decode_stream`]: ./struct.StripeDecoder.html#method.decode_stream
+pub struct DecodeStripes<B, R> {
+    pool: ErasureCoderPool<B>,
+    fragment_counts: FragmentCountsFuture,
+
+    // `None` until `fragment_counts` is resolved.
+    data_fragments: Option<usize>,
+    read_ahead: usize,
+    index: StripeIndex,
+    sources: Vec<Option<Source<R>>>,
+    next_stripe: usize,
+    decoding: VecDeque<(usize, DecodeFuture)>,
+}
+impl<B: BuildCoder, R: Read> DecodeStripes<B, R> {
+    /// Reads the fragments of the given stripe from the available sources.
+    fn read_stripe(
+        &mut self,
+        stripe: usize,
+        data_fragments: usize,
+    ) -> Result<Vec<(usize, FragmentBuf)>> {
+        let entry = &self.index.stripes[stripe];
+        let mut fragments = Vec::with_capacity(data_fragments);
+        for (i, source) in self.sources.iter_mut().enumerate() {
+            if fragments.len() == data_fragments {
+                break;
+            }
+            let result = match source {
+                None => continue,
+                Some(s) => s.read_fragment(entry.fragment_offsets[i], entry.fragment_lens[i]),
+            };
+            match result {
+                Ok(fragment) => fragments.push((i, fragment)),
+                Err(_) => *source = None,
+            }
+        }
+        track_assert_eq!(
+            fragments.len(),
+            data_fragments,
+            ErrorKind::InvalidInput,
+            "Too few available sources: stripe={}",
+            stripe
+        );
+        Ok(fragments)
+    }
+}
+impl<B: BuildCoder, R: Read> Stream for DecodeStripes<B, R> {
+    type Item = Vec<u8>;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let data_fragments = if let Some(n) = self.data_fragments {
+            n
+        } else {
+            let (data_fragments, fragments) = match track!(self.fragment_counts.poll())? {
+                Async::NotReady => return Ok(Async::NotReady),
+                Async::Ready(counts) => counts,
+            };
+            track_assert_eq!(
+                self.sources.len(),
+                fragments.get(),
+                ErrorKind::InvalidInput,
+                "The number of sources differs from the number of fragments"
+            );
+            self.data_fragments = Some(data_fragments.get());
+            data_fragments.get()
+        };
+
+        while self.decoding.len() < self.read_ahead && self.next_stripe < self.index.stripes.len() {
+            let stripe = self.next_stripe;
+            let fragments = track!(self.read_stripe(stripe, data_fragments))?;
+            let future = self.pool.decode_indexed(fragments);
+            self.decoding.push_back((stripe, Box::new(future)));
+            self.next_stripe += 1;
+        }
+
+        let (stripe, data) = match self.decoding.front_mut() {
+            None => return Ok(Async::Ready(None)),
+            Some((stripe, future)) => match track!(future.poll())? {
+                Async::NotReady => return Ok(Async::NotReady),
+                Async::Ready(data) => (*stripe, data),
+            },
+        };
+        self.decoding.pop_front();
+        track_assert_eq!(
+            data.len(),
+            self.index.stripes[stripe].data_len,
+            ErrorKind::CorruptedFragments,
+            "Unexpected stripe size: stripe={}",
+            stripe
+        );
+        Ok(Async::Ready(Some(data)))
+    }
+}
+
+/// A reader that decodes an object stripe by stripe.
+///
+/// This is created by calling [`StripeDecoder::decode_reader`] method.
+///
+/// [`StripeDecoder::decode_reader`]: ./struct.StripeDecoder.html#method.decode_reader
+pub struct StripeReader<B, R> {
+    stripes: DecodeStripes<B, R>,
+    executor: Option<InPlaceExecutor>,
+    buf: Vec<u8>,
+    position: usize,
+}
+impl<B: BuildCoder, R: Read> StripeReader<B, R> {
+    fn next_stripe(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.executor.is_none() {
+            let executor = track!(InPlaceExecutor::new().map_err(|e| ErrorKind::Other.cause(e)))?;
+            self.executor = Some(executor);
+        }
+        let executor = self.executor.as_mut().expect("Never fails");
+        let (stripe, _) = track!(executor
+            .run_future((&mut self.stripes).into_future())
+            .map_err(|e| ErrorKind::Other.cause(e)))?
+        .map_err(|(e, _)| e)?;
+        Ok(stripe)
+    }
+}
+impl<B: BuildCoder, R: Read> Read for StripeReader<B, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buf.len() {
+            match self.next_stripe() {
+                Err(e) => return Err(io::Error::other(e)),
+                Ok(None) => return Ok(0),
+                Ok(Some(stripe)) => {
+                    self.buf = stripe;
+                    self.position = 0;
+                }
+            }
+        }
+        let size = std::cmp::min(buf.len(), self.buf.len() - self.position);
+        buf[..size].copy_from_slice(&self.buf[self.position..][..size]);
+        self.position += size;
+        Ok(size)
+    }
+}
+
+/// Reads at most `stripe_size` bytes.
+///
+/// The result is shorter than `stripe_size` only if the reader reaches EOF.
+fn read_stripe<R: Read>(reader: &mut R, stripe_size: usize) -> Result<Vec<u8>> {
+    let mut stripe = Vec::with_capacity(stripe_size);
+    track!(reader
+        .take(stripe_size as u64)
+        .read_to_end(&mut stripe)
+        .map_err(|e| ErrorKind::Other.cause(e)))?;
+    Ok(stripe)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+    use std::result::Result;
+    use trackable::error::MainError;
+
+    use super::*;
+    use crate::reedsolomon::ReedSolomonCoder;
+    use crate::test_util::{non_zero, test_data};
+    use crate::ErasureCode;
+
+    #[test]
+    fn encode_stream_works() -> Result<(), MainError> {
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?;
+        let encoder = StripeEncoder::new(ErasureCoderPool::new(coder.clone()), non_zero(100)?);
+
+        let data = test_data(1050);
+        let chunks = data.chunks(33).map(|c| c.to_vec()).collect::<Vec<_>>();
+        let stripes = track!(fibers_global::execute(
+            encoder
+                .encode_stream(stream::iter_ok::<_, Error>(chunks))
+                .collect(),
+        ))?;
+        assert_eq!(stripes.len(), 11);
+
+        let mut index = StripeIndex::new();
+        for stripe in &stripes {
+            index.push(stripe);
+            let start = stripe.data_offset as usize;
+            let end = start + stripe.data_len;
+            let fragments = stripe.fragments.iter().map(|f| &f[..]).collect::<Vec<_>>();
+            assert_eq!(track!(coder.decode(&fragments[2..]))?, &data[start..end]);
+        }
+        assert_eq!(index.data_len(), 1050);
+
+        let mut sinks = vec![Vec::new(); 6];
+        assert_eq!(track!(encoder.encode_reader(&data[..], &mut sinks))?, index);
+        assert_eq!(
+            sinks[0].len() as u64,
+            index.stripes[10].fragment_offsets[0] + index.stripes[10].fragment_lens[0] as u64
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decoder_switches_failed_sources() -> Result<(), MainError> {
+        let pool =
+            ErasureCoderPool::new(track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(2)?))?);
+        let encoder = StripeEncoder::new(pool.clone(), non_zero(100)?);
+        let data = test_data(1050);
+        let mut sinks = vec![Vec::new(); 6];
+        let index = track!(encoder.encode_reader(&data[..], &mut sinks))?;
+
+        // The source #1 fails at the middle of the 4th stripe and the source #2 is unavailable
+        let sources = sinks
+            .iter()
+            .enumerate()
+            .map(|(i, s)| match i {
+                1 => Some(FailingReader(s[..100].to_vec())),
+                2 => None,
+                _ => Some(FailingReader(s.clone())),
+            })
+            .collect::<Vec<_>>();
+        let decoder = StripeDecoder::new(pool).read_ahead(non_zero(3)?);
+        let stripes = track!(fibers_global::execute(
+            track!(decoder.decode_stream(index.clone(), sources.clone()))?.collect(),
+        ))?;
+        assert_eq!(stripes.concat(), data);
+
+        let mut decoded = Vec::new();
+        let mut reader = track!(decoder.decode_reader(index.clone(), sources.clone()))?;
+        track_any_err!(reader.read_to_end(&mut decoded))?;
+        assert_eq!(decoded, data);
+
+        // The sources must correspond to all the fragments
+        assert_eq!(
+            decoder
+                .decode_stream(index.clone(), sources[1..].to_vec())
+                .map(|_| ())
+                .map_err(|e| *e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+
+        // The number of the fragments of the coder is checked when the stream is polled
+        let pool =
+            ErasureCoderPool::new(track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(1)?))?);
+        let stripes = track!(StripeDecoder::new(pool).decode_stream(index, sources))?;
+        assert_eq!(
+            fibers_global::execute(stripes.collect())
+                .map(|_| ())
+                .map_err(|e| *e.kind()),
+            Err(ErrorKind::InvalidInput)
+        );
+        Ok(())
+    }
+
+    /// A reader that fails when it reaches the end of the given bytes.
+    #[derive(Clone)]
+    struct FailingReader(Vec<u8>);
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() && !buf.is_empty() {
+                return Err(io::Error::other("Failed"));
+            }
+            let size = std::cmp::min(self.0.len(), buf.len());
+            buf[..size].copy_from_slice(&self.0[..size]);
+            self.0.drain(..size);
+            Ok(size)
+        }
+    }
+}
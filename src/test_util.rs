@@ -0,0 +1,13 @@
+//! Fixtures shared by the tests of the coders.
+use std::num::NonZeroUsize;
+use trackable::error::{Failed, Failure};
+
+/// Returns the data of `len` bytes that is encoded in the tests.
+pub fn test_data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 + 3) as u8).collect()
+}
+
+/// Converts `n` into `NonZeroUsize`.
+pub fn non_zero(n: usize) -> Result<NonZeroUsize, Failure> {
+    Ok(track_assert_some!(NonZeroUsize::new(n), Failed))
+}
@@ -0,0 +1,270 @@
+use crate::{ErasureCode, Fragment};
+
+/// The maximum number of combinations of fragments tried for locating corrupted fragments.
+const MAX_TRIALS: usize = 256;
+
+/// The result of verifying fragments.
+///
+/// See [`ErasureCode::verify`] for details.
+///
+/// [`ErasureCode::verify`]: ./trait.ErasureCode.html#method.verify
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// The indices of the fragments that are not given.
+    pub missing: Vec<usize>,
+
+    /// The indices of the fragments that are detected as corrupted.
+    pub corrupt: Vec<usize>,
+
+    /// The indices of the fragments that are consistent with the parities of each other.
+    pub consistent: Vec<usize>,
+
+    /// The indices of the fragments in which no corruption is detected but
+    /// the consistency of which could not be checked
+    /// (e.g., there are no redundant fragments or too many fragments are corrupted).
+    pub unverified: Vec<usize>,
+
+    /// The positions (within the given slice) of the fragments of which indices could not be identified.
+    pub unidentified: Vec<usize>,
+}
+impl VerifyReport {
+    /// Returns `true` if all of the fragments are given and consistent.
+    pub fn is_healthy(&self) -> bool {
+        self.missing.is_empty()
+            && self.corrupt.is_empty()
+            && self.unverified.is_empty()
+            && self.unidentified.is_empty()
+    }
+}
+
+/// The information of a fragment that is obtained from its header.
+#[derive(Debug)]
+pub(crate) struct FragmentMeta {
+    pub index: usize,
+    pub data_len: usize,
+
+    /// Whether the fragment passed the integrity checks of the header (e.g., the payload checksum).
+    pub intact: bool,
+}
+
+/// Verifies the given fragments by using their headers parsed by `parse`.
+///
+/// The consistency is checked by comparing the fragments except for the first `header_size` bytes
+/// with the ones re-encoded from a subset of them.
+pub(crate) fn verify<C, F>(
+    coder: &mut C,
+    fragments: &[&Fragment],
+    header_size: usize,
+    parse: F,
+) -> VerifyReport
+where
+    C: ErasureCode + ?Sized,
+    F: Fn(&Fragment) -> Option<FragmentMeta>,
+{
+    let total = coder.fragments().get();
+    let mut report = VerifyReport::default();
+    let mut seen = vec![false; total];
+    let mut candidates = Vec::new();
+    for (position, &fragment) in fragments.iter().enumerate() {
+        match parse(fragment) {
+            Some(meta) if meta.index < total => {
+                if seen[meta.index] {
+                    continue;
+                }
+                seen[meta.index] = true;
+                if meta.intact {
+                    candidates.push((meta, fragment));
+                } else {
+                    report.corrupt.push(meta.index);
+                }
+            }
+            _ => report.unidentified.push(position),
+        }
+    }
+    report.missing = (0..total).filter(|&i| !seen[i]).collect();
+
+    // The most common size of the original data is regarded as the correct one
+    let data_len = candidates
+        .iter()
+        .map(|(m, _)| m.data_len)
+        .max_by_key(|&len| candidates.iter().filter(|(m, _)| m.data_len == len).count());
+    let (candidates, broken): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|(m, f)| {
+        Some(m.data_len) == data_len && coder.fragment_size(m.data_len) == Some(f.len())
+    });
+    report.corrupt.extend(broken.iter().map(|(m, _)| m.index));
+
+    let k = coder.data_fragments().get();
+    let consistent = if candidates.len() > k {
+        find_consistent(coder, &candidates, header_size)
+    } else {
+        None
+    };
+    if let Some(consistent) = consistent {
+        report.corrupt.extend(
+            candidates
+                .iter()
+                .map(|(m, _)| m.index)
+                .filter(|i| !consistent.contains(i)),
+        );
+        report.consistent = consistent;
+    } else {
+        report.unverified = candidates.iter().map(|(m, _)| m.index).collect();
+    }
+    report.corrupt.sort_unstable();
+    report.consistent.sort_unstable();
+    report.unverified.sort_unstable();
+    report
+}
+
+/// Verifies the given fragments by comparing those with the ones re-encoded from the decoded data.
+///
+/// This is used by the implementations that cannot identify the indices of fragments from their contents.
+pub(crate) fn verify_by_reencoding<C>(coder: &mut C, fragments: &[&Fragment]) -> VerifyReport
+where
+    C: ErasureCode + ?Sized,
+{
+    let total = coder.fragments().get();
+    let mut report = VerifyReport::default();
+    let mut seen = vec![false; total];
+    match coder.decode(fragments).and_then(|data| coder.encode(&data)) {
+        Ok(encoded) => {
+            for (position, &fragment) in fragments.iter().enumerate() {
+                if let Some(i) = (0..total).find(|&i| !seen[i] && encoded[i] == fragment) {
+                    seen[i] = true;
+                } else {
+                    report.unidentified.push(position);
+                }
+            }
+        }
+        Err(_) => report.unidentified = (0..fragments.len()).collect(),
+    }
+    report.missing = (0..total).filter(|&i| !seen[i]).collect();
+    let identified = (0..total).filter(|&i| seen[i]).collect::<Vec<_>>();
+    if identified.len() > coder.data_fragments().get() {
+        report.consistent = identified;
+    } else {
+        report.unverified = identified;
+    }
+    report
+}
+
+/// Re-encodes each `N`-subset of `candidates` and returns the indices of the fragments
+/// that agree with the first result confirmed by at least one other fragment.
+fn find_consistent<C>(
+    coder: &mut C,
+    candidates: &[(FragmentMeta, &Fragment)],
+    header_size: usize,
+) -> Option<Vec<usize>>
+where
+    C: ErasureCode + ?Sized,
+{
+    let k = coder.data_fragments().get();
+
+    // Enumerates the fragments to be excluded rather than the ones to be used,
+    // so that a single corrupted fragment is located within `candidates.len()` trials.
+    let mut excluded = (0..candidates.len() - k).collect::<Vec<_>>();
+    for _ in 0..MAX_TRIALS {
+        let chosen = candidates
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !excluded.contains(i))
+            .map(|(_, (m, f))| (m.index, *f))
+            .collect::<Vec<_>>();
+        let encoded = coder
+            .decode_indexed(&chosen)
+            .and_then(|data| coder.encode(&data));
+        if let Ok(encoded) = encoded {
+            let agreed = candidates
+                .iter()
+                .filter(|(m, f)| {
+                    encoded.get(m.index).and_then(|e| e.get(header_size..)) == f.get(header_size..)
+                })
+                .map(|(m, _)| m.index)
+                .collect::<Vec<_>>();
+            if agreed.len() > k {
+                return Some(agreed);
+            }
+        }
+        if !next_combination(&mut excluded, candidates.len()) {
+            break;
+        }
+    }
+    None
+}
+
+/// Advances `combination` to the next one in lexicographic order among the combinations of `0..n`.
+///
+/// Returns `false` if there is no next combination.
+fn next_combination(combination: &mut [usize], n: usize) -> bool {
+    let k = combination.len();
+    for i in (0..k).rev() {
+        if combination[i] < n - k + i {
+            combination[i] += 1;
+            for j in i + 1..k {
+                combination[j] = combination[j - 1] + 1;
+            }
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::result::Result;
+    use trackable::error::MainError;
+
+    use super::*;
+    use crate::reedsolomon::ReedSolomonCoder;
+    use crate::replica::ReplicaCoder;
+    use crate::test_util::{non_zero, test_data};
+
+    #[test]
+    fn verify_locates_corrupted_fragments() -> Result<(), MainError> {
+        let mut coder = track!(ReedSolomonCoder::new(non_zero(4)?, non_zero(3)?))?;
+        let data = test_data(1000);
+        let mut encoded = track!(coder.encode(&data))?;
+
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        let report = coder.verify(&fragments);
+        assert!(report.is_healthy());
+        assert_eq!(report.consistent, (0..7).collect::<Vec<_>>());
+
+        // Corrupts the payload of #2 and the header of #6
+        encoded[2][100] ^= 1;
+        encoded[6].truncate(4);
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        let report = coder.verify(&fragments);
+        assert_eq!(report.missing, vec![6]);
+        assert_eq!(report.corrupt, vec![2]);
+        assert_eq!(report.consistent, vec![0, 1, 3, 4, 5]);
+        assert_eq!(report.unidentified, vec![6]);
+        assert!(report.unverified.is_empty());
+
+        // Too many corrupted fragments to locate those
+        encoded[5][200] ^= 1;
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        let report = coder.verify(&fragments);
+        assert!(report.corrupt.is_empty());
+        assert_eq!(report.unverified, vec![0, 1, 2, 3, 4, 5]);
+
+        // No redundant fragments
+        let report = coder.verify(&fragments[..4]);
+        assert_eq!(report.unverified, vec![0, 1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_by_reencoding_works() -> Result<(), MainError> {
+        let mut coder = ReplicaCoder::new(non_zero(2)?, non_zero(2)?);
+        let mut encoded = track!(coder.encode(&[0, 1, 2, 3]))?;
+        encoded[3][0] = 10;
+
+        let fragments = encoded.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        let report = coder.verify(&fragments);
+        assert_eq!(report.consistent, vec![0, 1, 2]);
+        assert_eq!(report.missing, vec![3]);
+        assert_eq!(report.unidentified, vec![3]);
+        Ok(())
+    }
+}